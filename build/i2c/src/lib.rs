@@ -50,6 +50,30 @@ struct I2cController {
     ports: BTreeMap<String, I2cPort>,
     #[serde(default)]
     target: bool,
+
+    /// number of times to retry a transaction whose address is NACK'd
+    /// before returning the error (lost arbitration is always retried)
+    #[serde(default)]
+    retries: u8,
+
+    /// SCL low timeout in milliseconds, for buses with targets that stretch
+    /// the clock for longer than the default (25 ms) allows
+    #[serde(default)]
+    timeout_ms: Option<u8>,
+
+    /// target addresses on this controller that only the named tasks may
+    /// access; addresses that aren't listed may be accessed by any task --
+    /// except for the general call address (0), which no task may use
+    /// unless it is listed here
+    #[serde(default)]
+    restricted: Vec<I2cRestriction>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct I2cRestriction {
+    address: u8,
+    tasks: Vec<String>,
 }
 
 //
@@ -665,7 +689,32 @@ impl ConfigGenerator {
         ["##
         )?;
 
+        //
+        // The SCL low timeout must fit in the 12 bits of TIMEOUTA at the
+        // peripheral clock rate (as scaled by drv-stm32xx-i2c), and a timeout
+        // of 0 would fail every transaction.
+        //
+        let khz: u32 = if build_util::has_feature("h743")
+            || build_util::has_feature("h753")
+        {
+            100_000
+        } else {
+            16_000
+        };
+
+        let max = (4096 * 2048 - 1) / khz;
+
         for c in &self.controllers {
+            if let Some(ms) = c.timeout_ms {
+                if ms == 0 || u32::from(ms) > max {
+                    panic!(
+                        "I2C{} has invalid timeout-ms {ms} (must be 1-{})",
+                        c.controller,
+                        max.min(u8::MAX.into())
+                    );
+                }
+            }
+
             write!(
                 &mut s,
                 r##"
@@ -674,8 +723,10 @@ impl ConfigGenerator {
                 peripheral: Peripheral::I2c{controller},
                 notification: crate::notifications::I2C{controller}_IRQ_MASK,
                 registers: unsafe {{ &*device::I2C{controller}::ptr() }},
+                timeout_ms: {timeout_ms:?},
             }},"##,
                 controller = c.controller,
+                timeout_ms = c.timeout_ms,
             )?;
         }
 
@@ -686,6 +737,134 @@ impl ConfigGenerator {
     }}"##
         )?;
 
+        //
+        // Only controllers that have asked for retries are called out;
+        // everything else gets no retries.
+        //
+        if self.controllers.iter().all(|c| c.retries == 0) {
+            writeln!(
+                &mut s,
+                r##"
+    #[allow(dead_code)]
+    pub fn retries(_controller: drv_i2c_api::Controller) -> u8 {{
+        0
+    }}"##
+            )?;
+        } else {
+            write!(
+                &mut s,
+                r##"
+    #[allow(dead_code)]
+    pub fn retries(controller: drv_i2c_api::Controller) -> u8 {{
+        match controller {{"##
+            )?;
+
+            for c in self.controllers.iter().filter(|c| c.retries != 0) {
+                write!(
+                    &mut s,
+                    r##"
+            drv_i2c_api::Controller::I2C{controller} => {retries},"##,
+                    controller = c.controller,
+                    retries = c.retries,
+                )?;
+            }
+
+            writeln!(
+                &mut s,
+                r##"
+            _ => 0,
+        }}
+    }}"##
+            )?;
+        }
+
+        //
+        // Similarly, only restricted addresses are called out; any task may
+        // access any other address -- except for the general call address,
+        // which every device on the bus may act on, and which is therefore
+        // denied to all tasks unless explicitly restricted to some.
+        //
+        let restricted = self
+            .controllers
+            .iter()
+            .flat_map(|c| c.restricted.iter().map(move |r| (c.controller, r)))
+            .collect::<Vec<_>>();
+
+        if restricted.is_empty() {
+            writeln!(
+                &mut s,
+                r##"
+    #[allow(dead_code)]
+    pub fn permitted(
+        _controller: drv_i2c_api::Controller,
+        address: u8,
+        _task: usize,
+    ) -> bool {{
+        address != 0
+    }}"##
+            )?;
+
+            return Ok(());
+        }
+
+        write!(
+            &mut s,
+            r##"
+    #[allow(dead_code)]
+    #[allow(unused_variables)]
+    pub fn permitted(
+        controller: drv_i2c_api::Controller,
+        address: u8,
+        task: usize,
+    ) -> bool {{
+        match (controller, address) {{"##
+        )?;
+
+        let ids = build_util::task_ids();
+        let mut seen = HashSet::new();
+
+        for (controller, r) in restricted {
+            if !seen.insert((controller, r.address)) {
+                panic!(
+                    "address {:#x} restricted twice on I2C{controller}",
+                    r.address
+                );
+            }
+
+            let tasks = ids.names_to_ids(&r.tasks)?;
+
+            let permitted = if tasks.is_empty() {
+                "false".to_string()
+            } else {
+                format!(
+                    "matches!(task, {})",
+                    tasks
+                        .iter()
+                        .map(|t| t.to_string())
+                        .collect::<Vec<_>>()
+                        .join(" | ")
+                )
+            };
+
+            write!(
+                &mut s,
+                r##"
+            (drv_i2c_api::Controller::I2C{controller}, {address:#x}) => {{
+                {permitted}
+            }}"##,
+                address = r.address,
+            )?;
+        }
+
+        writeln!(
+            &mut s,
+            r##"
+            (_, 0) => false,
+            _ => true,
+        }}
+    }}"##
+        )?;
+
         Ok(())
     }
 
@@ -914,6 +1093,18 @@ impl ConfigGenerator {
     fn generate_device(&self, d: &I2cDevice, indent: usize) -> String {
         let (controller, port) = self.lookup_controller_port(d);
 
+        //
+        // Catch a reserved (or non-7-bit) address here rather than at run
+        // time, when every transaction to the device would fail.  (This
+        // must match drv_i2c_types::ReservedAddress.)
+        //
+        if d.address < 0x08 || d.address >= 0x7c {
+            panic!(
+                "device {} has invalid or reserved address {:#x}",
+                d.device, d.address
+            );
+        }
+
         let segment = match (d.mux, d.segment) {
             (Some(mux), Some(segment)) => {
                 let mux_count = self
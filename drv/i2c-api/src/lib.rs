@@ -135,17 +135,16 @@ impl I2cDevice {
 }
 
 impl I2cDevice {
-    fn response_code<V>(&self, code: u32, val: V) -> Result<V, ResponseCode> {
-        if code != 0 {
-            if let Some(_g) = userlib::extract_new_generation(code) {
-                panic!("i2c reset");
-            }
-
-            Err(ResponseCode::from_u32(code)
-                .ok_or(ResponseCode::BadResponse)?)
-        } else {
-            Ok(val)
-        }
+    ///
+    /// Returns the payload identifying this device to the I2C server.
+    ///
+    fn payload(&self) -> [u8; 4] {
+        Marshal::marshal(&(
+            self.address,
+            self.controller,
+            self.port,
+            self.segment,
+        ))
     }
 
     ///
@@ -176,17 +175,54 @@ impl I2cDevice {
         let (code, _) = sys_send(
             self.task,
             Op::WriteRead as u16,
-            &Marshal::marshal(&(
-                self.address,
-                self.controller,
-                self.port,
-                self.segment,
-            )),
+            &self.payload(),
             response.as_mut_bytes(),
             &[Lease::from(reg.as_bytes()), Lease::from(val.as_mut_bytes())],
         );
 
-        self.response_code(code, val)
+        response_code(code, val)
+    }
+
+    ///
+    /// Reads the register described by `reg`.
+    ///
+    pub fn read_register<A, V>(
+        &self,
+        reg: Register<A, V>,
+    ) -> Result<V, ResponseCode>
+    where
+        A: IntoBytes + Immutable,
+        V: IntoBytes + FromBytes,
+    {
+        self.read_reg(reg.address)
+    }
+
+    ///
+    /// Writes `val` to the register described by `reg`, as a single write
+    /// of the register address followed by the value.
+    ///
+    pub fn write_register<A, V>(
+        &self,
+        reg: Register<A, V>,
+        val: V,
+    ) -> Result<(), ResponseCode>
+    where
+        A: IntoBytes + Immutable,
+        V: IntoBytes + Immutable,
+    {
+        let addr = reg.address.as_bytes();
+        let val = val.as_bytes();
+        let len = addr.len() + val.len();
+        let mut buf = [0u8; 32];
+
+        if len > buf.len() {
+            return Err(ResponseCode::BadArg);
+        }
+
+        buf[..addr.len()].copy_from_slice(addr);
+        buf[addr.len()..len].copy_from_slice(val);
+
+        self.write(&buf[..len])
     }
 
     ///
@@ -204,17 +240,12 @@ impl I2cDevice {
         let (code, _) = sys_send(
             self.task,
             Op::WriteRead as u16,
-            &Marshal::marshal(&(
-                self.address,
-                self.controller,
-                self.port,
-                self.segment,
-            )),
+            &self.payload(),
             response.as_mut_bytes(),
             &[Lease::from(reg.as_bytes()), Lease::from(buf)],
         );
 
-        self.response_code(code, response)
+        response_code(code, response)
     }
 
     ///
@@ -234,17 +265,12 @@ impl I2cDevice {
         let (code, _) = sys_send(
             self.task,
             Op::WriteReadBlock as u16,
-            &Marshal::marshal(&(
-                self.address,
-                self.controller,
-                self.port,
-                self.segment,
-            )),
+            &self.payload(),
             response.as_mut_bytes(),
             &[Lease::from(reg.as_bytes()), Lease::from(buf)],
         );
 
-        self.response_code(code, response)
+        response_code(code, response)
     }
 
     ///
@@ -261,17 +287,12 @@ impl I2cDevice {
         let (code, _) = sys_send(
             self.task,
             Op::WriteRead as u16,
-            &Marshal::marshal(&(
-                self.address,
-                self.controller,
-                self.port,
-                self.segment,
-            )),
+            &self.payload(),
             response.as_mut_bytes(),
             &[Lease::read_only(&[]), Lease::from(val.as_mut_bytes())],
         );
 
-        self.response_code(code, val)
+        response_code(code, val)
     }
 
     ///
@@ -285,17 +306,12 @@ impl I2cDevice {
         let (code, _) = sys_send(
             self.task,
             Op::WriteRead as u16,
-            &Marshal::marshal(&(
-                self.address,
-                self.controller,
-                self.port,
-                self.segment,
-            )),
+            &self.payload(),
             response.as_mut_bytes(),
             &[Lease::read_only(&[]), Lease::from(buf)],
         );
 
-        self.response_code(code, response)
+        response_code(code, response)
     }
 
     ///
@@ -308,17 +324,40 @@ impl I2cDevice {
         let (code, _) = sys_send(
             self.task,
             Op::WriteRead as u16,
-            &Marshal::marshal(&(
-                self.address,
-                self.controller,
-                self.port,
-                self.segment,
-            )),
+            &self.payload(),
+            response.as_mut_bytes(),
+            &[Lease::from(buffer), Lease::read_only(&[])],
+        );
+
+        response_code(code, ())
+    }
+
+    ///
+    /// Writes a buffer to the general call address (0x00) on this device's
+    /// bus (and segment), as some devices require for a software reset or
+    /// address assignment.  Every device on the bus that honors general
+    /// calls will see the write; the address of this device itself is
+    /// ignored.  Only tasks that the I2C configuration explicitly permits
+    /// to access address 0 may do this; any other caller gets
+    /// [`ResponseCode::AccessDenied`].
+    ///
+    pub fn general_call(&self, buffer: &[u8]) -> Result<(), ResponseCode> {
+        let device = I2cDevice {
+            address: ReservedAddress::GeneralCall as u8,
+            ..*self
+        };
+
+        let mut response = 0_usize;
+
+        let (code, _) = sys_send(
+            self.task,
+            Op::GeneralCall as u16,
+            &device.payload(),
             response.as_mut_bytes(),
             &[Lease::from(buffer), Lease::read_only(&[])],
         );
 
-        self.response_code(code, ())
+        response_code(code, ())
     }
 
     ///
@@ -344,12 +383,7 @@ impl I2cDevice {
         let (code, _) = sys_send(
             self.task,
             Op::WriteRead as u16,
-            &Marshal::marshal(&(
-                self.address,
-                self.controller,
-                self.port,
-                self.segment,
-            )),
+            &self.payload(),
             response.as_mut_bytes(),
             &[
                 Lease::from(buffer),
@@ -359,7 +393,7 @@ impl I2cDevice {
             ],
         );
 
-        self.response_code(code, val)
+        response_code(code, val)
     }
 
     ///
@@ -386,12 +420,7 @@ impl I2cDevice {
         let (code, _) = sys_send(
             self.task,
             Op::WriteReadBlock as u16,
-            &Marshal::marshal(&(
-                self.address,
-                self.controller,
-                self.port,
-                self.segment,
-            )),
+            &self.payload(),
             response.as_mut_bytes(),
             &[
                 Lease::from(buffer),
@@ -401,7 +430,35 @@ impl I2cDevice {
             ],
         );
 
-        self.response_code(code, response)
+        response_code(code, response)
+    }
+
+    ///
+    /// Performs a chain of writes and SMBus block reads, each a (write, read)
+    /// pair in which either (but not both) may be empty, with the pairs
+    /// separated by repeated starts rather than STOPs (as some devices
+    /// require of consecutive block reads).  Unlike [`read_block`], each
+    /// non-empty read buffer receives its block's byte count followed by the
+    /// block itself, and so must be one byte longer than the largest
+    /// expected block.  Returns the total number of bytes written into the
+    /// read buffers.
+    ///
+    pub fn write_read_block_chained<const N: usize>(
+        &self,
+        chain: [(&[u8], &mut [u8]); N],
+    ) -> Result<usize, ResponseCode> {
+        let leases = chain.map(|(w, r)| [Lease::from(w), Lease::from(r)]);
+        let mut response = 0_usize;
+
+        let (code, _) = sys_send(
+            self.task,
+            Op::WriteReadBlockChained as u16,
+            &self.payload(),
+            response.as_mut_bytes(),
+            leases.as_flattened(),
+        );
+
+        response_code(code, response)
     }
 
     ///
@@ -421,12 +478,7 @@ impl I2cDevice {
         let (code, _) = sys_send(
             self.task,
             Op::WriteRead as u16,
-            &Marshal::marshal(&(
-                self.address,
-                self.controller,
-                self.port,
-                self.segment,
-            )),
+            &self.payload(),
             response.as_mut_bytes(),
             &[
                 Lease::from(first),
@@ -436,7 +488,7 @@ impl I2cDevice {
             ],
         );
 
-        self.response_code(code, ())
+        response_code(code, ())
     }
 
     ///
@@ -463,12 +515,7 @@ impl I2cDevice {
         let (code, _) = sys_send(
             self.task,
             Op::WriteRead as u16,
-            &Marshal::marshal(&(
-                self.address,
-                self.controller,
-                self.port,
-                self.segment,
-            )),
+            &self.payload(),
             response.as_mut_bytes(),
             &[
                 Lease::from(first),
@@ -480,6 +527,227 @@ impl I2cDevice {
             ],
         );
 
-        self.response_code(code, val)
+        response_code(code, val)
+    }
+
+    ///
+    /// Like [`read_reg`], but with SMBus Packet Error Checking:  the server
+    /// will read the PEC that the device sends after the value, and fail
+    /// with [`ResponseCode::BadPec`] if it doesn't match.
+    ///
+    pub fn read_reg_pec<R, V>(&self, reg: R) -> Result<V, ResponseCode>
+    where
+        R: IntoBytes + Immutable,
+        V: IntoBytes + FromBytes,
+    {
+        let mut val = V::new_zeroed();
+        let mut response = 0_usize;
+
+        let (code, _) = sys_send(
+            self.task,
+            Op::WriteReadPec as u16,
+            &self.payload(),
+            response.as_mut_bytes(),
+            &[Lease::from(reg.as_bytes()), Lease::from(val.as_mut_bytes())],
+        );
+
+        response_code(code, val)
     }
+
+    ///
+    /// Like [`read_block`], but with SMBus Packet Error Checking.  The PEC
+    /// covers the byte count as well as the payload; as with [`read_block`],
+    /// neither the byte count nor the PEC is present in the returned payload.
+    ///
+    pub fn read_block_pec<R: IntoBytes + Immutable>(
+        &self,
+        reg: R,
+        buf: &mut [u8],
+    ) -> Result<usize, ResponseCode> {
+        let mut response = 0_usize;
+
+        let (code, _) = sys_send(
+            self.task,
+            Op::WriteReadBlockPec as u16,
+            &self.payload(),
+            response.as_mut_bytes(),
+            &[Lease::from(reg.as_bytes()), Lease::from(buf)],
+        );
+
+        response_code(code, response)
+    }
+
+    ///
+    /// Like [`write`], but with SMBus Packet Error Checking:  the server will
+    /// compute the PEC over the transaction and send it after the buffer.
+    /// A device that detects a PEC mismatch will generally NACK the PEC
+    /// byte, which is returned as [`ResponseCode::NoRegister`].
+    ///
+    pub fn write_pec(&self, buffer: &[u8]) -> Result<(), ResponseCode> {
+        let mut response = 0_usize;
+
+        let (code, _) = sys_send(
+            self.task,
+            Op::WriteReadPec as u16,
+            &self.payload(),
+            response.as_mut_bytes(),
+            &[Lease::from(buffer), Lease::read_only(&[])],
+        );
+
+        response_code(code, ())
+    }
+
+    ///
+    /// Performs an SMBus Process Call:  writes the command code `cmd`
+    /// followed by `word` (little-endian), and then -- with a repeated start
+    /// -- reads back the word that the device replies with.
+    ///
+    pub fn process_call(
+        &self,
+        cmd: u8,
+        word: u16,
+    ) -> Result<u16, ResponseCode> {
+        let [lo, hi] = word.to_le_bytes();
+        let val = self.read_reg::<[u8; 3], [u8; 2]>([cmd, lo, hi])?;
+
+        Ok(u16::from_le_bytes(val))
+    }
+
+    ///
+    /// Performs an SMBus Block Process Call:  writes the command code `cmd`,
+    /// a byte count and then that many bytes, and then -- with a repeated
+    /// start -- performs a block read into `rbuf`, returning the number of
+    /// bytes read.  So that the write can be sent directly from the caller's
+    /// buffer, the bytes to be written are `wbuf[2..]`; the first two bytes
+    /// of `wbuf` are overwritten with the command code and the count.  As
+    /// with [`read_block`], the byte count read is only returned from the
+    /// function and is not present in `rbuf`.
+    ///
+    pub fn block_process_call(
+        &self,
+        cmd: u8,
+        wbuf: &mut [u8],
+        rbuf: &mut [u8],
+    ) -> Result<usize, ResponseCode> {
+        let count = wbuf
+            .len()
+            .checked_sub(2)
+            .and_then(|len| u8::try_from(len).ok())
+            .ok_or(ResponseCode::BadArg)?;
+
+        wbuf[0] = cmd;
+        wbuf[1] = count;
+
+        let mut response = 0_usize;
+
+        let (code, _) = sys_send(
+            self.task,
+            Op::WriteReadBlock as u16,
+            &self.payload(),
+            response.as_mut_bytes(),
+            &[Lease::from(&wbuf[..]), Lease::from(rbuf)],
+        );
+
+        response_code(code, response)
+    }
+
+    ///
+    /// Performs an SMBus Alert Response Address read on this device's bus
+    /// (and segment), returning the address of a device asserting
+    /// SMBALERT# -- or `None` if no device is asserting it.  (The address of
+    /// this device itself is ignored.)  The responding device deasserts
+    /// SMBALERT#; if several devices are asserting it, the one with the
+    /// lowest address responds, and the caller should repeat the read
+    /// until it returns `None`.
+    ///
+    pub fn alert_response(&self) -> Result<Option<u8>, ResponseCode> {
+        let ara = I2cDevice {
+            address: smbus::ALERT_RESPONSE_ADDRESS,
+            ..*self
+        };
+
+        match ara.read::<u8>() {
+            Ok(byte) => Ok(Some(byte >> 1)),
+            Err(ResponseCode::NoDevice) => Ok(None),
+            Err(code) => Err(code),
+        }
+    }
+}
+
+///
+/// Translates the response code from the I2C server into a result -- with
+/// a server that has restarted being fatal to its client.
+///
+fn response_code<V>(code: u32, val: V) -> Result<V, ResponseCode> {
+    if code != 0 {
+        if let Some(_g) = userlib::extract_new_generation(code) {
+            panic!("i2c reset");
+        }
+
+        Err(ResponseCode::from_u32(code).ok_or(ResponseCode::BadResponse)?)
+    } else {
+        Ok(val)
+    }
+}
+
+///
+/// Returns the transaction statistics that the I2C server running as `task`
+/// has accumulated for `controller`.
+///
+pub fn controller_stats(
+    task: TaskId,
+    controller: Controller,
+) -> Result<ControllerStats, ResponseCode> {
+    let mut stats = ControllerStats::default();
+
+    let (code, _) = sys_send(
+        task,
+        Op::ControllerStats as u16,
+        &[controller as u8],
+        stats.as_mut_bytes(),
+        &[],
+    );
+
+    response_code(code, stats)
+}
+
+///
+/// Fully reinitializes `controller` in the I2C server running as `task`,
+/// e.g. to recover it from a fault without restarting the server.
+///
+pub fn reinit_controller(
+    task: TaskId,
+    controller: Controller,
+) -> Result<(), ResponseCode> {
+    let (code, _) = sys_send(
+        task,
+        Op::ReinitController as u16,
+        &[controller as u8],
+        &mut [],
+        &[],
+    );
+
+    response_code(code, ())
+}
+
+///
+/// Fills `records` with the most recent transactions performed by the I2C
+/// server running as `task`, most recent first, returning the number of
+/// records filled in.
+///
+pub fn recent_transactions(
+    task: TaskId,
+    records: &mut [TransactionRecord],
+) -> Result<usize, ResponseCode> {
+    let mut response = 0_usize;
+
+    let (code, _) = sys_send(
+        task,
+        Op::RecentTransactions as u16,
+        &[],
+        response.as_mut_bytes(),
+        &[Lease::from(records.as_mut_bytes())],
+    );
+
+    response_code(code, response)
 }
@@ -0,0 +1,282 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Driver for generic 24Cxx-style I2C EEPROMs
+
+use crate::Validate;
+use drv_i2c_api::*;
+use userlib::hl::sleep_for;
+
+/// Time between polls for the completion of a write cycle
+const WRITE_POLL_MS: u64 = 1;
+
+/// Number of polls before we give up on a write cycle completing.  Parts in
+/// this family specify a maximum write cycle time of 5 or 10 ms.
+const WRITE_POLL_LIMIT: u32 = 20;
+
+/// The largest amount of data that we will put in a single write.  A write
+/// must be assembled (with its word address) in a buffer on our stack, so
+/// we bound it -- and a page write may be split into several writes of this
+/// size without ill effect.  (Reads go directly into the caller's buffer,
+/// and so need no such bound.)
+const MAX_WRITE: usize = 128;
+
+///
+/// The organization of a particular 24Cxx part.  The family varies in total
+/// size, in page size, and in whether the word address is sent as one byte or
+/// two.  Any address bits above those sent in the word address are instead
+/// sent in the low bits of the 7-bit device address (e.g., A8-A10 on the
+/// 24C16); the device address given to [`At24Cxx::new`] should have those
+/// bits cleared.
+///
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Geometry {
+    /// Total number of bytes in the EEPROM
+    pub size: u32,
+
+    /// Number of bytes in a write page; a write may not cross a page boundary
+    pub page_size: u16,
+
+    /// Number of bytes in the word address (1 or 2)
+    pub address_bytes: u8,
+}
+
+pub const AT24C01: Geometry = Geometry::new(128, 8, 1);
+pub const AT24C02: Geometry = Geometry::new(256, 8, 1);
+pub const AT24C04: Geometry = Geometry::new(512, 16, 1);
+pub const AT24C08: Geometry = Geometry::new(1024, 16, 1);
+pub const AT24C16: Geometry = Geometry::new(2048, 16, 1);
+pub const AT24C32: Geometry = Geometry::new(4096, 32, 2);
+pub const AT24C64: Geometry = Geometry::new(8192, 32, 2);
+pub const AT24C128: Geometry = Geometry::new(16384, 64, 2);
+pub const AT24C256: Geometry = Geometry::new(32768, 64, 2);
+pub const AT24C512: Geometry = Geometry::new(65536, 128, 2);
+
+impl Geometry {
+    pub const fn new(size: u32, page_size: u16, address_bytes: u8) -> Self {
+        assert!(address_bytes == 1 || address_bytes == 2);
+        assert!(page_size.is_power_of_two());
+        Self {
+            size,
+            page_size,
+            address_bytes,
+        }
+    }
+
+    /// Number of bytes addressable by the word address alone
+    const fn block_size(&self) -> u32 {
+        1 << (8 * self.address_bytes as u32)
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Error {
+    /// The low-level I2C communication returned an error
+    I2cError(ResponseCode),
+
+    /// The starting address is out of range for the EEPROM
+    InvalidAddress(u32),
+
+    /// In a multi-byte read or write, the end address is out of range
+    InvalidEndAddress(u32),
+
+    /// The device returned fewer bytes than were requested
+    ShortRead(u32),
+
+    /// The device did not complete its write cycle in the allotted time
+    WriteTimeout(u32),
+}
+
+impl From<ResponseCode> for Error {
+    fn from(err: ResponseCode) -> Self {
+        Error::I2cError(err)
+    }
+}
+
+impl From<Error> for ResponseCode {
+    fn from(err: Error) -> Self {
+        match err {
+            Error::I2cError(code) => code,
+            Error::WriteTimeout(_) => ResponseCode::NoDevice,
+            _ => ResponseCode::BadArg,
+        }
+    }
+}
+
+///
+/// A 24Cxx EEPROM of a given [`Geometry`].
+///
+/// Rather than waiting out the worst-case write cycle time, writes use
+/// acknowledge polling: the device will NACK its address until the write
+/// cycle completes, so we re-send the word address until it is ACK'd.  Note
+/// that this means that a device that is absent will manifest as a
+/// [`Error::WriteTimeout`] on write (but as an I2C error on read).
+///
+pub struct At24Cxx {
+    device: I2cDevice,
+    geometry: Geometry,
+}
+
+impl core::fmt::Display for At24Cxx {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "at24cxx: {}", &self.device)
+    }
+}
+
+impl At24Cxx {
+    pub fn new(device: I2cDevice, geometry: Geometry) -> Self {
+        Self { device, geometry }
+    }
+
+    pub fn size(&self) -> u32 {
+        self.geometry.size
+    }
+
+    /// Checks that `len` bytes starting at `addr` fit in the EEPROM
+    fn check_range(&self, addr: u32, len: usize) -> Result<(), Error> {
+        if addr >= self.geometry.size {
+            return Err(Error::InvalidAddress(addr));
+        }
+
+        let end = u32::try_from(len)
+            .ok()
+            .and_then(|len| addr.checked_add(len))
+            .ok_or(Error::InvalidEndAddress(u32::MAX))?;
+
+        if end > self.geometry.size {
+            return Err(Error::InvalidEndAddress(end));
+        }
+
+        Ok(())
+    }
+
+    /// Returns the `I2cDevice` that covers `addr`, along with the word
+    /// address (big-endian) and its length.
+    fn target(&self, addr: u32) -> (I2cDevice, [u8; 2], usize) {
+        let bytes = self.geometry.address_bytes as usize;
+        let block = addr / self.geometry.block_size();
+        let word = (addr % self.geometry.block_size()) as u16;
+
+        let wa = if bytes == 1 {
+            [word as u8, 0]
+        } else {
+            word.to_be_bytes()
+        };
+
+        let device = I2cDevice {
+            address: self.device.address | block as u8,
+            ..self.device
+        };
+
+        (device, wa, bytes)
+    }
+
+    /// Reads from `addr` into `buf`, which is filled completely.
+    ///
+    /// `addr` must be below the EEPROM's size, and `addr + buf.len()` must
+    /// be at or below it; otherwise this function will return an error.
+    pub fn read_at(&self, addr: u32, buf: &mut [u8]) -> Result<(), Error> {
+        self.check_range(addr, buf.len())?;
+
+        let mut offset = 0;
+
+        while offset < buf.len() {
+            let at = addr + offset as u32;
+
+            //
+            // A sequential read won't carry into the address bits that live
+            // in the device address, so don't let a read cross a block.
+            //
+            let block_left =
+                self.geometry.block_size() - at % self.geometry.block_size();
+            let len = (buf.len() - offset).min(block_left as usize);
+
+            let (device, wa, n) = self.target(at);
+            let chunk = &mut buf[offset..offset + len];
+
+            let nread = if n == 1 {
+                device.read_reg_into(wa[0], chunk)?
+            } else {
+                device.read_reg_into(wa, chunk)?
+            };
+
+            if nread != len {
+                return Err(Error::ShortRead(at));
+            }
+
+            offset += len;
+        }
+
+        Ok(())
+    }
+
+    /// Writes `buf` to the EEPROM starting at `addr`, splitting the write at
+    /// page boundaries and waiting for each write cycle to complete.
+    ///
+    /// `addr` must be below the EEPROM's size, and `addr + buf.len()` must
+    /// be at or below it; otherwise this function will return an error.
+    pub fn write_at(&self, addr: u32, buf: &[u8]) -> Result<(), Error> {
+        self.check_range(addr, buf.len())?;
+
+        let page = self.geometry.page_size as u32;
+        let mut offset = 0;
+
+        while offset < buf.len() {
+            let at = addr + offset as u32;
+            let page_left = page - (at % page);
+            let len =
+                (buf.len() - offset).min(MAX_WRITE).min(page_left as usize);
+
+            let (device, wa, n) = self.target(at);
+            let mut out = [0u8; 2 + MAX_WRITE];
+
+            out[..n].copy_from_slice(&wa[..n]);
+            out[n..n + len].copy_from_slice(&buf[offset..offset + len]);
+
+            device.write(&out[..n + len])?;
+            self.wait_for_write(&device, &wa[..n], at)?;
+
+            offset += len;
+        }
+
+        Ok(())
+    }
+
+    /// Polls the device until it acknowledges, indicating that its internal
+    /// write cycle has completed.
+    fn wait_for_write(
+        &self,
+        device: &I2cDevice,
+        wa: &[u8],
+        addr: u32,
+    ) -> Result<(), Error> {
+        for _ in 0..WRITE_POLL_LIMIT {
+            sleep_for(WRITE_POLL_MS);
+
+            //
+            // Sending just the word address sets the device's address
+            // pointer without modifying anything, which makes it a
+            // harmless way to see if the device is back.
+            //
+            match device.write(wa) {
+                Ok(()) => return Ok(()),
+                Err(ResponseCode::NoDevice) => continue,
+                Err(code) => return Err(Error::I2cError(code)),
+            }
+        }
+
+        Err(Error::WriteTimeout(addr))
+    }
+}
+
+impl Validate<Error> for At24Cxx {
+    fn validate(device: &I2cDevice) -> Result<bool, Error> {
+        //
+        // There is nothing to identify a 24Cxx; the best we can do is see
+        // that something answers a (one byte addressed) read at address 0.
+        //
+        device.read_reg::<u8, u8>(0)?;
+        Ok(true)
+    }
+}
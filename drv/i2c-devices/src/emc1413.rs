@@ -0,0 +1,133 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Driver for the EMC1413 temperature sensor
+
+use crate::{TempSensor, Validate};
+use drv_i2c_api::*;
+use userlib::units::*;
+
+#[allow(dead_code)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Register {
+    InternalTempHiByte = 0x00,
+    External1TempHiByte = 0x01,
+    Status = 0x02,
+    Config = 0x03,
+    ConversionRate = 0x04,
+    OneShot = 0x0F,
+    External1TempLoByte = 0x10,
+    External2TempHiByte = 0x23,
+    External2TempLoByte = 0x24,
+    InternalTempLoByte = 0x29,
+    ProductId = 0xFD,
+    ManufacturerId = 0xFE,
+}
+
+/// Bit in [`Register::Config`] that selects the extended temperature range,
+/// in which readings are offset by 64 degrees
+const CONFIG_RANGE: u8 = 1 << 2;
+
+/// Offset applied to temperatures in the extended range
+const EXTENDED_OFFSET: f32 = 64.0;
+
+#[derive(Debug)]
+pub enum Error {
+    BadRegisterRead { reg: Register, code: ResponseCode },
+    BadRegisterWrite { reg: Register, code: ResponseCode },
+}
+
+impl From<Error> for ResponseCode {
+    fn from(err: Error) -> Self {
+        match err {
+            Error::BadRegisterRead { code, .. }
+            | Error::BadRegisterWrite { code, .. } => code,
+        }
+    }
+}
+
+/// Selects which of the three channels this sensor reads
+#[derive(Copy, Clone)]
+pub enum Target {
+    Internal,
+    External1,
+    External2,
+}
+
+pub struct Emc1413 {
+    device: I2cDevice,
+    target: Target,
+}
+
+impl core::fmt::Display for Emc1413 {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "emc1413: {}", &self.device)
+    }
+}
+
+impl Emc1413 {
+    pub fn new(device: &I2cDevice, target: Target) -> Self {
+        // By default, the chip runs at 4 conversions per second, which is
+        // plenty fast for our use case.
+        Self {
+            device: *device,
+            target,
+        }
+    }
+
+    fn read_reg(&self, reg: Register) -> Result<u8, Error> {
+        self.device
+            .read_reg::<u8, u8>(reg as u8)
+            .map_err(|code| Error::BadRegisterRead { reg, code })
+    }
+
+    pub fn write_reg(&self, reg: Register, value: u8) -> Result<(), Error> {
+        self.device
+            .write(&[reg as u8, value])
+            .map_err(|code| Error::BadRegisterWrite { reg, code })
+    }
+}
+
+impl Validate<Error> for Emc1413 {
+    fn validate(device: &I2cDevice) -> Result<bool, Error> {
+        let dev = Emc1413::new(device, Target::Internal);
+        let mfg = dev.read_reg(Register::ManufacturerId)?;
+        let id = dev.read_reg(Register::ProductId)?;
+
+        Ok(mfg == 0x5D && id == 0x21)
+    }
+}
+
+impl TempSensor<Error> for Emc1413 {
+    fn read_temperature(&self) -> Result<Celsius, Error> {
+        let (hi, lo) = match self.target {
+            Target::Internal => {
+                (Register::InternalTempHiByte, Register::InternalTempLoByte)
+            }
+            Target::External1 => {
+                (Register::External1TempHiByte, Register::External1TempLoByte)
+            }
+            Target::External2 => {
+                (Register::External2TempHiByte, Register::External2TempLoByte)
+            }
+        };
+
+        // Reading the high byte latches the low byte until it is read
+        let hi = self.read_reg(hi)?;
+        let lo = self.read_reg(lo)?;
+
+        // The low byte holds eighths of a degree in its top three bits
+        let t = f32::from(hi) + f32::from(lo >> 5) * 0.125f32;
+
+        // The range is a configuration choice that we must respect
+        // rather than impose, so check it on every read.
+        let config = self.read_reg(Register::Config)?;
+
+        Ok(Celsius(if config & CONFIG_RANGE != 0 {
+            t - EXTENDED_OFFSET
+        } else {
+            t
+        }))
+    }
+}
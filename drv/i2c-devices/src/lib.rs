@@ -9,7 +9,9 @@
 //! - [`adm1272`]: ADM1272 hot swap controller
 //! - [`adt7420`]: ADT7420 temperature sensor
 //! - [`at24csw080`]: AT24CSW080 serial EEPROM
+//! - [`at24cxx`]: Generic 24Cxx serial EEPROMs
 //! - [`ds2482`]: DS2482-100 1-wire initiator
+//! - [`emc1413`]: EMC1413 temperature sensor
 //! - [`emc2305`]: EMC2305 fan driver
 //! - [`isl68224`]: ISL68224 power controller
 //! - [`lm5066`]: LM5066 hot swap controller
@@ -236,8 +238,10 @@ pub trait Validate<T: core::convert::Into<drv_i2c_api::ResponseCode>> {
 pub mod adm1272;
 pub mod adt7420;
 pub mod at24csw080;
+pub mod at24cxx;
 pub mod bmr491;
 pub mod ds2482;
+pub mod emc1413;
 pub mod emc2305;
 pub mod isl68224;
 pub mod lm5066;
@@ -13,10 +13,13 @@ use hubpack::SerializedSize;
 use num_derive::FromPrimitive;
 use num_traits::FromPrimitive as _;
 use serde::{Deserialize, Serialize};
+use zerocopy::{FromBytes, Immutable, IntoBytes, KnownLayout};
 
 use derive_idol_err::IdolError;
 use enum_kinds::EnumKind;
 
+pub mod smbus;
+
 #[derive(FromPrimitive, Eq, PartialEq)]
 pub enum Op {
     WriteRead = 1,
@@ -32,10 +35,76 @@ pub enum Op {
     /// Specifically, the caller can construct the array `[reg, size, data[0],
     /// data[1], ...]` and pass it to a normal `WriteRead` operation.
     ///
-    /// If we encounter a device which requires multiple block reads in a row
-    /// without interruption, this logic would not work, but that would be a
-    /// very strange device indeed.
+    /// A device which requires multiple block reads in a row without
+    /// interruption should instead use [`Op::WriteReadBlockChained`].
     WriteReadBlock = 2,
+
+    /// A `WriteReadPec` operation is a `WriteRead` in which each write/read
+    /// pair is an SMBus transaction protected by a Packet Error Code: the
+    /// server appends the PEC to a write-only transaction, and reads and
+    /// validates the PEC that terminates a transaction with a read.  A PEC
+    /// mismatch results in [`ResponseCode::BadPec`].
+    WriteReadPec = 3,
+
+    /// A `WriteReadBlockPec` operation is to `WriteReadBlock` what
+    /// `WriteReadPec` is to `WriteRead`: the final read is an SMBus block
+    /// read, and every transaction is protected by a Packet Error Code.
+    WriteReadBlockPec = 4,
+
+    /// Returns the [`ControllerStats`] for the controller named by the
+    /// single-byte message.
+    ControllerStats = 5,
+
+    /// Fills the single write lease with the most recent transactions as
+    /// [`TransactionRecord`]s (most recent first), returning the number of
+    /// records written.
+    RecentTransactions = 6,
+
+    /// In a `WriteReadBlockChained` operation, **every** non-empty read is
+    /// an SMBus block read, and the write/read pairs are separated by
+    /// repeated starts rather than STOPs.  Because the caller can't
+    /// otherwise know how much of each read lease was filled, each read
+    /// lease receives its block's byte count followed by the block itself
+    /// (and so must be one byte longer than the largest expected block).
+    /// The reply is the total number of bytes written into read leases.
+    WriteReadBlockChained = 7,
+
+    /// Fully reinitializes the controller named by the single-byte message
+    /// (as at boot), without disturbing any other controller.  Each of the
+    /// controller's buses is then recovered as after a bus error:  its SCL
+    /// is wiggled and its muxes are reset (to be set by the next
+    /// transaction).
+    ReinitController = 8,
+
+    /// Like `WriteRead`, but addressed to the general call address (0x00),
+    /// which is otherwise rejected as reserved.  The address in the message
+    /// must be 0, every read lease must be empty (a general call is a
+    /// broadcast write), and the caller must be explicitly permitted to
+    /// access address 0.
+    GeneralCall = 9,
+}
+
+impl Op {
+    /// Returns true if this operation uses SMBus Packet Error Checking
+    pub fn pec(&self) -> bool {
+        matches!(self, Op::WriteReadPec | Op::WriteReadBlockPec)
+    }
+
+    /// Returns true if the final read of this operation is an SMBus block
+    /// read
+    pub fn block(&self) -> bool {
+        matches!(self, Op::WriteReadBlock | Op::WriteReadBlockPec)
+    }
+
+    /// Returns true if this operation chains block reads
+    pub fn chained(&self) -> bool {
+        matches!(self, Op::WriteReadBlockChained)
+    }
+
+    /// Returns true if this operation is a general call
+    pub fn general_call(&self) -> bool {
+        matches!(self, Op::GeneralCall)
+    }
 }
 
 /// The response code returned from the I2C server.  These response codes pretty
@@ -109,6 +178,12 @@ pub enum ResponseCode {
     IllegalLeaseCount,
     /// Too much data -- or not enough buffer
     TooMuchData,
+    /// SMBus Packet Error Code did not match the transaction
+    BadPec,
+    /// Arbitration was lost to another initiator on the bus
+    ArbitrationLost,
+    /// Calling task is not permitted to access the device
+    AccessDenied,
 }
 
 ///
@@ -157,6 +232,19 @@ pub enum ReservedAddress {
     TenBit11 = 0b1111_111,
 }
 
+impl ReservedAddress {
+    ///
+    /// Checks that `addr` is a 7-bit address that is not reserved.
+    ///
+    pub fn check(addr: u8) -> Result<(), ResponseCode> {
+        if addr > 0x7f || Self::from_u8(addr).is_some() {
+            Err(ResponseCode::ReservedAddress)
+        } else {
+            Ok(())
+        }
+    }
+}
+
 ///
 /// The port index for a given I2C device.  Some controllers can have multiple
 /// ports (which themselves are connected to different I2C buses), but only
@@ -233,3 +321,84 @@ pub enum Segment {
     S15 = 15,
     S16 = 16,
 }
+
+///
+/// Per-controller transaction statistics kept by the I2C server.  Each
+/// write/read lease pair (and each failed attempt to configure a mux before
+/// a transaction) counts as one transaction.
+///
+#[derive(
+    Copy, Clone, Debug, Default, FromBytes, IntoBytes, Immutable, KnownLayout,
+)]
+#[repr(C)]
+pub struct ControllerStats {
+    /// Total transactions
+    pub transactions: u32,
+    /// Transactions that failed because no device acknowledged its address
+    pub nodevice: u32,
+    /// Transactions that failed for any other reason
+    pub errors: u32,
+    /// Failed transactions that resulted in the bus being reset
+    pub resets: u32,
+    /// Attempts that lost arbitration (including those that were retried)
+    pub arbitration: u32,
+}
+
+///
+/// A summary of a single transaction, as recorded by the I2C server.
+///
+#[derive(
+    Copy, Clone, Debug, Default, FromBytes, IntoBytes, Immutable, KnownLayout,
+)]
+#[repr(C)]
+pub struct TransactionRecord {
+    /// Time at which the transaction completed, in ticks
+    pub timestamp: u64,
+    /// Controller, as a [`Controller`]
+    pub controller: u8,
+    /// Port index
+    pub port: u8,
+    /// Device address
+    pub address: u8,
+    /// Mux and segment in their marshalled form, or 0 if none
+    pub segment: u8,
+    /// Bytes written (saturating at 255)
+    pub wlen: u8,
+    /// Bytes read (saturating at 255)
+    pub rlen: u8,
+    /// Number of times the transaction was retried
+    pub retries: u8,
+    /// 0 on success; a [`ResponseCode`] on failure
+    pub code: u8,
+    /// The first bytes written (if any)
+    pub wdata: [u8; TransactionRecord::CAPTURE],
+    /// The first bytes read (if any)
+    pub rdata: [u8; TransactionRecord::CAPTURE],
+}
+
+impl TransactionRecord {
+    /// Number of bytes captured from each of the write and the read
+    pub const CAPTURE: usize = 4;
+}
+
+///
+/// A device register, described by its address (of type `A`) and the type
+/// of its value (`V`).  A multi-byte value should be described with one of
+/// the byte-order-aware types in [`zerocopy::byteorder`] (e.g.,
+/// `U16<BigEndian>`), making the register's endianness part of its
+/// description rather than something each access must get right.
+///
+#[derive(Copy, Clone, Debug)]
+pub struct Register<A, V> {
+    pub address: A,
+    value: core::marker::PhantomData<V>,
+}
+
+impl<A, V> Register<A, V> {
+    pub const fn new(address: A) -> Self {
+        Self {
+            address,
+            value: core::marker::PhantomData,
+        }
+    }
+}
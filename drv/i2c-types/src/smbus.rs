@@ -0,0 +1,97 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! SMBus-specific support
+//!
+//! This is shared between the I2C servers (which compute and check Packet
+//! Error Codes on the wire) and clients (which may want to compute them
+//! themselves, e.g. to construct a PEC-protected block write).
+
+///
+/// The SMBus Alert Response Address (ARA).  A read from this address is
+/// answered by a device asserting SMBALERT#, which replies with its own
+/// address (in the upper seven bits of the byte read).
+///
+pub const ALERT_RESPONSE_ADDRESS: u8 = 0x0c;
+
+///
+/// An accumulator for an SMBus Packet Error Code (PEC).  The PEC is a CRC-8
+/// with the polynomial x^8 + x^2 + x + 1 and an initial value of zero,
+/// computed over every byte of a transaction -- including the address byte
+/// that begins each (repeated) start, but not including the ACK/NACK bits.
+///
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct Pec(u8);
+
+impl Pec {
+    const POLYNOMIAL: u8 = 0x07;
+
+    pub fn new() -> Self {
+        Self(0)
+    }
+
+    /// Accumulate a single byte
+    pub fn update(&mut self, byte: u8) {
+        let mut crc = self.0 ^ byte;
+
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 {
+                (crc << 1) ^ Self::POLYNOMIAL
+            } else {
+                crc << 1
+            };
+        }
+
+        self.0 = crc;
+    }
+
+    /// Accumulate a slice of bytes
+    pub fn update_slice(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.update(*byte);
+        }
+    }
+
+    /// Accumulate the address byte for a write to the 7-bit address `addr`
+    pub fn write_address(&mut self, addr: u8) {
+        self.update(addr << 1);
+    }
+
+    /// Accumulate the address byte for a read from the 7-bit address `addr`
+    pub fn read_address(&mut self, addr: u8) {
+        self.update((addr << 1) | 1);
+    }
+
+    /// Return the PEC for the bytes accumulated thus far
+    pub fn value(&self) -> u8 {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_value() {
+        // The standard CRC-8 check value for "123456789"
+        let mut pec = Pec::new();
+        pec.update_slice(b"123456789");
+        assert_eq!(pec.value(), 0xf4);
+    }
+
+    #[test]
+    fn residue() {
+        // A receiver that accumulates the PEC byte itself must end at zero
+        let mut pec = Pec::new();
+        pec.write_address(0x58);
+        pec.update(0x8b);
+        pec.read_address(0x58);
+        pec.update_slice(&[0x02, 0x1f, 0x9a]);
+
+        let value = pec.value();
+        pec.update(value);
+        assert_eq!(pec.value(), 0);
+    }
+}
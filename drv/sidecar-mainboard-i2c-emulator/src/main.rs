@@ -33,9 +33,7 @@ fn main() -> ! {
 
                 let (addr, _, _, _) = Marshal::unmarshal(payload)?;
 
-                if let Some(_) = ReservedAddress::from_u8(addr) {
-                    return Err(ResponseCode::ReservedAddress);
-                }
+                ReservedAddress::check(addr)?;
 
                 ringbuf_entry!(Trace::Addr(addr));
 
@@ -67,6 +65,21 @@ fn main() -> ! {
                 caller.reply(0);
                 Ok(())
             }
+
+            //
+            // We emulate nothing beyond plain transactions:  we have no PEC
+            // to check, no controller to report statistics or transactions
+            // of (or to reinitialize), and no bus on which to chain or
+            // broadcast -- and pretending otherwise would rob the caller of
+            // what they asked for.
+            //
+            Op::WriteReadPec
+            | Op::WriteReadBlockPec
+            | Op::ControllerStats
+            | Op::RecentTransactions
+            | Op::WriteReadBlockChained
+            | Op::ReinitController
+            | Op::GeneralCall => Err(ResponseCode::OperationNotSupported),
         });
     }
 }
@@ -13,6 +13,7 @@ use drv_stm32xx_sys_api::{Mode, OutputType, PinSet, Pull, Speed, Sys};
 
 use fixedmap::*;
 use ringbuf::*;
+use static_cell::ClaimOnceCell;
 use userlib::*;
 
 task_slot!(SYS, sys);
@@ -223,8 +224,44 @@ fn configure_mux(
     Ok(())
 }
 
-#[derive(Copy, Clone, PartialEq)]
+///
+/// Called when a device on an enabled mux segment has failed to acknowledge
+/// its address.  That's usually just an absent device -- but it may also be
+/// that the mux itself has dropped off the bus (or been reset) out from
+/// under us.  Ask the mux driver, returning the more specific error if so
+/// (and marking our mux state as unknown), or the original error if not.
+///
+fn check_segment(
+    code: ResponseCode,
+    muxmap: &mut MuxMap,
+    controller: &I2cController<'_>,
+    port: PortIndex,
+    (id, segment): (Mux, Segment),
+    muxes: &[I2cMux<'_>],
+) -> ResponseCode {
+    let result = find_mux(controller, port, muxes, id, |mux| {
+        let pending = mux.driver.check_segment(mux, controller, segment)?;
+
+        if pending != 0 {
+            ringbuf_entry!(Trace::MuxInterrupts(mux.address, pending));
+        }
+
+        Ok(())
+    });
+
+    match result {
+        Ok(()) => code,
+        Err(err) => {
+            ringbuf_entry!(Trace::SegmentCheckFailed(err.into()));
+            muxmap.insert((controller.controller, port), MuxState::Unknown);
+            err
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, counters::Count)]
 enum Trace {
+    #[count(skip)]
     None,
     SegmentOnError((Mux, Segment)),
     Error(u8, ResponseCodeU8),
@@ -237,9 +274,15 @@ enum Trace {
     SegmentFailed(ResponseCodeU8),
     ConfigureFailed(ResponseCodeU8),
     Wiggles(u8),
+    Retry(u8, #[count(children)] ResponseCodeU8),
+    RetriesExhausted(u8, #[count(children)] ResponseCodeU8),
+    MuxInterrupts(u8, u8),
+    SegmentCheckFailed(#[count(children)] ResponseCodeU8),
+    Quarantined((Mux, Segment), ResponseCodeU8),
+    Reinit(Controller),
 }
 
-ringbuf!(Trace, 160, Trace::None);
+counted_ringbuf!(Trace, 160, Trace::None);
 
 fn reset(
     controller: &I2cController<'_>,
@@ -283,6 +326,27 @@ fn reset_needed(code: ResponseCode) -> bool {
     )
 }
 
+///
+/// The number of times we will retry a transaction that lost arbitration to
+/// another initiator on the bus.
+///
+const ARBITRATION_RETRIES: u8 = 4;
+
+///
+/// Returns the number of times a transaction that failed with the specified
+/// error should be retried.  Errors that may well go away on their own are
+/// retried:  lost arbitration (which is to be expected on a bus with another
+/// initiator) always is, and a NACK on the address (e.g., an EEPROM in its
+/// write cycle) is if the controller has been configured with `retries`.
+///
+fn retry_limit(code: ResponseCode, retries: u8) -> u8 {
+    match code {
+        ResponseCode::ArbitrationLost => ARBITRATION_RETRIES,
+        ResponseCode::NoDevice => retries,
+        _ => 0,
+    }
+}
+
 fn reset_if_needed(
     code: ResponseCode,
     controller: &I2cController<'_>,
@@ -295,6 +359,45 @@ fn reset_if_needed(
     }
 }
 
+///
+/// A variant of [`reset`] that will first wiggle the SCL lines via
+/// [`wiggle_scl`], to free a target that is holding SDA low.
+///
+fn reset_and_wiggle(
+    controller: &I2cController<'_>,
+    port: PortIndex,
+    muxes: &[I2cMux<'_>],
+    muxmap: &mut MuxMap,
+    pins: &[I2cPins],
+) {
+    let sys = SYS.get_task_id();
+    let sys = Sys::from(sys);
+
+    for pin in pins
+        .iter()
+        .filter(|p| p.controller == controller.controller)
+        .filter(|p| p.port == port)
+    {
+        wiggle_scl(&sys, pin.scl, pin.sda);
+
+        //
+        // [`wiggle_scl`] puts our pins in output (and input) mode; set
+        // them back to be configured for I2C before we reset.
+        //
+        for gpio_pin in &[pin.scl, pin.sda] {
+            sys.gpio_configure_alternate(
+                *gpio_pin,
+                OutputType::OpenDrain,
+                Speed::Low,
+                Pull::None,
+                pin.function,
+            );
+        }
+    }
+
+    reset(controller, port, muxes, muxmap);
+}
+
 ///
 /// A variant of [`reset_if_needed`] that will also wiggle the SCL lines
 /// via [`wiggle_scl`].
@@ -308,32 +411,7 @@ fn reset_and_wiggle_if_needed(
     pins: &[I2cPins],
 ) {
     if reset_needed(code) {
-        let sys = SYS.get_task_id();
-        let sys = Sys::from(sys);
-
-        for pin in pins
-            .iter()
-            .filter(|p| p.controller == controller.controller)
-            .filter(|p| p.port == port)
-        {
-            wiggle_scl(&sys, pin.scl, pin.sda);
-
-            //
-            // [`wiggle_scl`] puts our pins in output (and input) mode; set
-            // them back to be configured for I2C before we reset.
-            //
-            for gpio_pin in &[pin.scl, pin.sda] {
-                sys.gpio_configure_alternate(
-                    *gpio_pin,
-                    OutputType::OpenDrain,
-                    Speed::Low,
-                    Pull::None,
-                    pin.function,
-                );
-            }
-        }
-
-        reset(controller, port, muxes, muxmap);
+        reset_and_wiggle(controller, port, muxes, muxmap, pins);
     }
 }
 
@@ -359,6 +437,229 @@ enum MuxState {
 type MuxMap =
     FixedMap<(Controller, PortIndex), MuxState, { i2c_config::NMUXEDBUSES }>;
 
+/// Number of transactions retained for [`Op::RecentTransactions`]
+const NRECENT: usize = 16;
+
+///
+/// Per-controller statistics and a history of recent transactions, as
+/// reported by [`Op::ControllerStats`] and [`Op::RecentTransactions`].
+///
+/// This is big enough that it is kept in a static rather than on our stack,
+/// and must therefore be [`init`](Diagnostics::init)ialized before use.
+///
+struct Diagnostics {
+    stats: [(Option<Controller>, ControllerStats); i2c_config::NCONTROLLERS],
+    recent: [TransactionRecord; NRECENT],
+    next: usize,
+    nrecent: usize,
+}
+
+impl Diagnostics {
+    const fn new() -> Self {
+        const STATS: ControllerStats = ControllerStats {
+            transactions: 0,
+            nodevice: 0,
+            errors: 0,
+            resets: 0,
+            arbitration: 0,
+        };
+
+        const RECORD: TransactionRecord = TransactionRecord {
+            timestamp: 0,
+            controller: 0,
+            port: 0,
+            address: 0,
+            segment: 0,
+            wlen: 0,
+            rlen: 0,
+            retries: 0,
+            code: 0,
+            wdata: [0; TransactionRecord::CAPTURE],
+            rdata: [0; TransactionRecord::CAPTURE],
+        };
+
+        Self {
+            stats: [(None, STATS); i2c_config::NCONTROLLERS],
+            recent: [RECORD; NRECENT],
+            next: 0,
+            nrecent: 0,
+        }
+    }
+
+    /// Associates our per-controller statistics with `controllers`
+    fn init(&mut self, controllers: &[I2cController<'_>]) {
+        for ((c, _), controller) in self.stats.iter_mut().zip(controllers) {
+            *c = Some(controller.controller);
+        }
+    }
+
+    fn find(&mut self, controller: Controller) -> Option<&mut ControllerStats> {
+        self.stats
+            .iter_mut()
+            .find(|(c, _)| *c == Some(controller))
+            .map(|(_, stats)| stats)
+    }
+
+    fn arbitration_lost(&mut self, controller: Controller) {
+        if let Some(stats) = self.find(controller) {
+            stats.arbitration = stats.arbitration.wrapping_add(1);
+        }
+    }
+
+    fn stats(&mut self, controller: Controller) -> Option<ControllerStats> {
+        self.find(controller).map(|stats| *stats)
+    }
+
+    ///
+    /// Records a transaction on `controller`, as described by `record`;
+    /// `result` is the outcome of the transaction.
+    ///
+    fn record(
+        &mut self,
+        controller: Controller,
+        mut record: TransactionRecord,
+        result: Result<(), ResponseCode>,
+    ) {
+        if let Some(stats) = self.find(controller) {
+            stats.transactions = stats.transactions.wrapping_add(1);
+
+            match result {
+                Ok(()) => {}
+                Err(ResponseCode::NoDevice) => {
+                    stats.nodevice = stats.nodevice.wrapping_add(1);
+                }
+                Err(code) => {
+                    stats.errors = stats.errors.wrapping_add(1);
+
+                    if reset_needed(code) {
+                        stats.resets = stats.resets.wrapping_add(1);
+                    }
+                }
+            }
+        }
+
+        record.timestamp = sys_get_timer().now;
+        record.code = match result {
+            Ok(()) => 0,
+            Err(code) => code as u8,
+        };
+
+        self.recent[self.next] = record;
+        self.next = (self.next + 1) % NRECENT;
+        self.nrecent = (self.nrecent + 1).min(NRECENT);
+    }
+
+    /// Returns recorded transactions, most recent first
+    fn recent(&self) -> impl Iterator<Item = &TransactionRecord> {
+        (1..=self.nrecent)
+            .map(|i| &self.recent[(self.next + NRECENT - i) % NRECENT])
+    }
+}
+
+///
+/// The number of consecutive transactions on a segment that must fail in a
+/// way that requires the bus to be reset before we quarantine the segment.
+///
+const QUARANTINE_THRESHOLD: u8 = 3;
+
+/// How long a segment stays quarantined before it is allowed to be retested
+const QUARANTINE_MS: u64 = 10_000;
+
+/// The number of failing segments that we can track at once
+const NQUARANTINE: usize = 8;
+
+type SegmentKey = (Controller, PortIndex, (Mux, Segment));
+
+#[derive(Copy, Clone)]
+struct SegmentHealth {
+    key: SegmentKey,
+    failures: u8,
+    until: u64,
+}
+
+///
+/// Tracks mux segments whose transactions keep wedging the bus.  A segment
+/// that fails [`QUARANTINE_THRESHOLD`] times in a row is quarantined: for
+/// the next [`QUARANTINE_MS`], transactions on it fail with
+/// [`ResponseCode::SegmentDisconnected`] without touching the bus, sparing
+/// everything else on the bus the resets.  Once the quarantine expires, the
+/// next transaction serves as a retest:  success clears the segment, while
+/// failure quarantines it again.
+///
+struct Quarantine {
+    segments: [Option<SegmentHealth>; NQUARANTINE],
+}
+
+impl Quarantine {
+    const fn new() -> Self {
+        Self {
+            segments: [None; NQUARANTINE],
+        }
+    }
+
+    fn find(&mut self, key: SegmentKey) -> Option<&mut SegmentHealth> {
+        self.segments.iter_mut().flatten().find(|h| h.key == key)
+    }
+
+    fn check(&mut self, key: SegmentKey, now: u64) -> Result<(), ResponseCode> {
+        match self.find(key) {
+            Some(health) if health.until > now => {
+                Err(ResponseCode::SegmentDisconnected)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn record(
+        &mut self,
+        key: SegmentKey,
+        result: Result<(), ResponseCode>,
+        now: u64,
+    ) {
+        let code = match result {
+            Ok(()) => {
+                for slot in self.segments.iter_mut() {
+                    if matches!(slot, Some(h) if h.key == key) {
+                        *slot = None;
+                    }
+                }
+                return;
+            }
+            Err(code) if reset_needed(code) => code,
+            Err(_) => return,
+        };
+
+        if self.find(key).is_none() {
+            //
+            // Take an empty slot if there is one, or else the slot of a
+            // segment that isn't currently quarantined.  If every slot holds
+            // a quarantined segment, we have bigger problems than this one.
+            //
+            let Some(slot) = self.segments.iter_mut().find(|slot| match slot {
+                None => true,
+                Some(h) => h.until <= now,
+            }) else {
+                return;
+            };
+
+            *slot = Some(SegmentHealth {
+                key,
+                failures: 0,
+                until: 0,
+            });
+        }
+
+        if let Some(health) = self.find(key) {
+            health.failures = health.failures.saturating_add(1);
+
+            if health.failures >= QUARANTINE_THRESHOLD {
+                ringbuf_entry!(Trace::Quarantined(key.2, code.into()));
+                health.until = now + QUARANTINE_MS;
+            }
+        }
+    }
+}
+
 #[export_name = "main"]
 fn main() -> ! {
     let controllers = i2c_config::controllers();
@@ -369,6 +670,18 @@ fn main() -> ! {
     let mut portmap = PortMap::default();
     let mut muxmap = MuxMap::default();
 
+    //
+    // Our diagnostics and quarantine are too large for our stack, so they
+    // live in statics.
+    //
+    let (diagnostics, quarantine) = {
+        static STATE: ClaimOnceCell<(Diagnostics, Quarantine)> =
+            ClaimOnceCell::new((Diagnostics::new(), Quarantine::new()));
+        STATE.claim()
+    };
+
+    diagnostics.init(&controllers);
+
     // Turn the actual peripheral on so that we can interact with it.
     turn_on_i2c(&controllers);
     configure_pins(&controllers, &pins, &mut portmap);
@@ -381,7 +694,12 @@ fn main() -> ! {
 
     loop {
         hl::recv_without_notification(&mut buffer, |op, msg| match op {
-            Op::WriteRead | Op::WriteReadBlock => {
+            Op::WriteRead
+            | Op::WriteReadBlock
+            | Op::WriteReadPec
+            | Op::WriteReadBlockPec
+            | Op::WriteReadBlockChained
+            | Op::GeneralCall => {
                 let lease_count = msg.lease_count();
 
                 let (payload, caller) = msg
@@ -395,13 +713,46 @@ fn main() -> ! {
                 let (addr, controller, port, mux) =
                     Marshal::unmarshal(payload)?;
 
-                if ReservedAddress::from_u8(addr).is_some() {
-                    return Err(ResponseCode::ReservedAddress);
+                let mut record = TransactionRecord {
+                    controller: payload[1],
+                    port: payload[2],
+                    address: addr,
+                    segment: payload[3],
+                    ..Default::default()
+                };
+
+                if op.general_call() {
+                    //
+                    // The general call address is reserved precisely because
+                    // every device may act on it; it is only accepted when
+                    // explicitly asked for, only for writes, and only from
+                    // tasks that the configuration permits it (which, by
+                    // default, is none of them).
+                    //
+                    if addr != ReservedAddress::GeneralCall as u8 {
+                        return Err(ResponseCode::BadArg);
+                    }
+                } else {
+                    ReservedAddress::check(addr)?;
+                }
+
+                if !i2c_config::permitted(
+                    controller,
+                    addr,
+                    caller.task_id().index(),
+                ) {
+                    return Err(ResponseCode::AccessDenied);
                 }
 
                 let controller = lookup_controller(&controllers, controller)?;
                 validate_port(&pins, controller.controller, port)?;
 
+                let key = mux.map(|mux| (controller.controller, port, mux));
+
+                if let Some(key) = key {
+                    quarantine.check(key, sys_get_timer().now)?;
+                }
+
                 configure_port(&mut portmap, controller, port, &pins);
 
                 match configure_mux(&mut muxmap, controller, port, mux, &muxes)
@@ -409,17 +760,71 @@ fn main() -> ! {
                     Ok(_) => {}
                     Err(code) => {
                         ringbuf_entry!(Trace::MuxError(code.into()));
-                        reset_if_needed(
+
+                        if let Some(key) = key {
+                            quarantine.record(
+                                key,
+                                Err(code),
+                                sys_get_timer().now,
+                            );
+                        }
+
+                        diagnostics.record(
+                            controller.controller,
+                            record,
+                            Err(code),
+                        );
+
+                        //
+                        // A mux that can't be talked to is as likely to be
+                        // behind a target holding SDA low as anything else:
+                        // recover the bus exactly as we would for an error
+                        // on the transaction itself.
+                        //
+                        reset_and_wiggle_if_needed(
                             code,
                             controller,
                             port,
                             &muxes,
                             &mut muxmap,
+                            &pins,
                         );
                         return Err(code);
                     }
                 }
 
+                if op.chained() || op.general_call() {
+                    //
+                    // Once a chain has started, we can't bail out of it
+                    // without leaving the bus held -- and a general call
+                    // must not get as far as broadcasting only some of its
+                    // writes -- so check all of our leases before we start.
+                    //
+                    for i in (0..lease_count).step_by(2) {
+                        let winfo = caller
+                            .borrow(i)
+                            .info()
+                            .ok_or(ResponseCode::BadArg)?;
+                        let rinfo = caller
+                            .borrow(i + 1)
+                            .info()
+                            .ok_or(ResponseCode::BadArg)?;
+
+                        let writable = rinfo.len == 0
+                            || rinfo
+                                .attributes
+                                .contains(LeaseAttributes::WRITE);
+
+                        if !winfo.attributes.contains(LeaseAttributes::READ)
+                            || !writable
+                            || (winfo.len == 0 && rinfo.len == 0)
+                            || (op.general_call() && rinfo.len != 0)
+                        {
+                            return Err(ResponseCode::BadArg);
+                        }
+                    }
+                }
+
                 let mut total = 0;
 
                 //
@@ -445,35 +850,163 @@ fn main() -> ! {
                         return Err(ResponseCode::BadArg);
                     }
 
-                    if winfo.len > 255 || rinfo.len > 255 {
-                        // For now, we don't support writing or reading more
-                        // than 255 bytes.
-                        return Err(ResponseCode::BadArg);
-                    }
-
                     let mut nread = 0;
 
-                    let controller_result = controller.write_read(
-                        addr,
-                        winfo.len,
-                        |pos| wbuf.read_at(pos),
-                        // Only the final read operation in a WriteReadBlock is
-                        // a block read; everything else is a normal read.
-                        if op == Op::WriteReadBlock && i == lease_count - 2 {
+                    //
+                    // Only the final read operation in a block operation is a
+                    // block read; everything else is a normal read -- unless
+                    // we are chaining block reads, in which case every read
+                    // is a block read (and its lease starts with the count).
+                    //
+                    let chained = op.chained() && rinfo.len != 0;
+                    let offset = usize::from(chained);
+
+                    let rlen =
+                        if chained || (op.block() && i == lease_count - 2) {
                             ReadLength::Variable
                         } else {
                             ReadLength::Fixed(rinfo.len)
-                        },
-                        |pos, byte| {
+                        };
+
+                    let link = match (i == 0, i == lease_count - 2) {
+                        (true, true) => Link::Only,
+                        (true, false) => Link::First,
+                        (false, false) => Link::Middle,
+                        (false, true) => Link::Last,
+                    };
+
+                    //
+                    // A link in the middle of a chain can't be retried:  the
+                    // bus is no longer where the chain left it.  And a NACK
+                    // from the Alert Response Address merely means that no
+                    // device is asserting SMBALERT#, so it isn't worth
+                    // retrying (or, below, suspecting the segment over).
+                    //
+                    let alert = addr == smbus::ALERT_RESPONSE_ADDRESS;
+
+                    let retries = if op.chained() && i != 0 {
+                        None
+                    } else if alert {
+                        Some(0)
+                    } else {
+                        Some(i2c_config::retries(controller.controller))
+                    };
+
+                    let mut attempt = 0;
+                    let mut rdata = [0u8; TransactionRecord::CAPTURE];
+
+                    let controller_result = loop {
+                        let getbyte = |pos| wbuf.read_at(pos);
+
+                        let putbyte = |pos, byte| {
                             if pos + 1 > nread {
                                 nread = pos + 1;
                             }
 
-                            rbuf.write_at(pos, byte)
-                        },
+                            if let Some(b) = rdata.get_mut(pos) {
+                                *b = byte;
+                            }
+
+                            rbuf.write_at(pos + offset, byte)
+                        };
+
+                        let result = if op.chained() {
+                            controller.write_read_link(
+                                addr, winfo.len, getbyte, rlen, putbyte, link,
+                            )
+                        } else if op.pec() {
+                            controller.write_read_pec(
+                                addr, winfo.len, getbyte, rlen, putbyte,
+                            )
+                        } else {
+                            controller.write_read(
+                                addr, winfo.len, getbyte, rlen, putbyte,
+                            )
+                        };
+
+                        let code = match result {
+                            Ok(()) => break result,
+                            Err(code) => code,
+                        };
+
+                        if code == ResponseCode::ArbitrationLost {
+                            diagnostics.arbitration_lost(controller.controller);
+                        }
+
+                        let limit = match retries {
+                            Some(retries) => retry_limit(code, retries),
+                            None => 0,
+                        };
+
+                        if attempt >= limit {
+                            if limit != 0 {
+                                ringbuf_entry!(Trace::RetriesExhausted(
+                                    addr,
+                                    code.into()
+                                ));
+                            }
+
+                            break result;
+                        }
+
+                        //
+                        // Our next attempt will wait for the bus to go idle
+                        // before issuing its START, so all we need to do here
+                        // is back off exponentially: 1, 2, 4, ... ticks.
+                        //
+                        ringbuf_entry!(Trace::Retry(addr, code.into()));
+                        hl::sleep_for(1 << attempt.min(6));
+                        attempt += 1;
+                        nread = 0;
+                        rdata = [0; TransactionRecord::CAPTURE];
+                    };
+
+                    record.wlen = u8::try_from(winfo.len).unwrap_or(u8::MAX);
+                    record.rlen = u8::try_from(nread).unwrap_or(u8::MAX);
+                    record.retries = attempt;
+                    record.rdata = rdata;
+
+                    for (pos, b) in record.wdata.iter_mut().enumerate() {
+                        *b = if pos < winfo.len {
+                            wbuf.read_at(pos).unwrap_or(0)
+                        } else {
+                            0
+                        };
+                    }
+
+                    diagnostics.record(
+                        controller.controller,
+                        record,
+                        controller_result,
                     );
+
                     match controller_result {
                         Err(code) => {
+                            let code = match mux {
+                                Some(mux)
+                                    if code == ResponseCode::NoDevice
+                                        && !alert =>
+                                {
+                                    check_segment(
+                                        code,
+                                        &mut muxmap,
+                                        controller,
+                                        port,
+                                        mux,
+                                        &muxes,
+                                    )
+                                }
+                                _ => code,
+                            };
+
+                            if let Some(key) = key {
+                                quarantine.record(
+                                    key,
+                                    Err(code),
+                                    sys_get_timer().now,
+                                );
+                            }
+
                             //
                             // NoDevice errors aren't hugely interesting --
                             // but on any other error, we want to record the
@@ -498,15 +1031,104 @@ fn main() -> ! {
                             );
                             return Err(code);
                         }
+                        Ok(_) if chained => {
+                            rbuf.write_at(0, nread as u8)
+                                .ok_or(ResponseCode::BadArg)?;
+                            total += nread + 1;
+                        }
                         Ok(_) => {
                             total += nread;
                         }
                     }
                 }
 
+                if let Some(key) = key {
+                    quarantine.record(key, Ok(()), sys_get_timer().now);
+                }
+
                 caller.reply(total);
                 Ok(())
             }
+
+            Op::ControllerStats => {
+                let (&controller, caller) = msg
+                    .fixed::<u8, ControllerStats>()
+                    .ok_or(ResponseCode::BadArg)?;
+
+                let controller = Controller::from_u8(controller)
+                    .ok_or(ResponseCode::BadController)?;
+
+                let stats = diagnostics
+                    .stats(controller)
+                    .ok_or(ResponseCode::BadController)?;
+
+                caller.reply(stats);
+                Ok(())
+            }
+
+            Op::ReinitController => {
+                let (&controller, caller) =
+                    msg.fixed::<u8, ()>().ok_or(ResponseCode::BadArg)?;
+
+                let controller = Controller::from_u8(controller)
+                    .ok_or(ResponseCode::BadController)?;
+                let controller = lookup_controller(&controllers, controller)?;
+
+                ringbuf_entry!(Trace::Reinit(controller.controller));
+                controller.reinitialize();
+                sys_irq_control(controller.notification, true);
+
+                //
+                // Nothing is in flight (we're the only one who would have
+                // started it), but a controller that needed reinitializing
+                // may well have left a target holding SDA low -- which no
+                // amount of reinitializing will clear.  Recover each of the
+                // controller's ports (each of which has exactly one entry in
+                // `pins`) as we would after a bus error, which also resets
+                // its muxes.
+                //
+                for pin in pins
+                    .iter()
+                    .filter(|p| p.controller == controller.controller)
+                {
+                    configure_port(&mut portmap, controller, pin.port, &pins);
+                    reset_and_wiggle(
+                        controller,
+                        pin.port,
+                        &muxes,
+                        &mut muxmap,
+                        &pins,
+                    );
+                }
+
+                caller.reply(());
+                Ok(())
+            }
+
+            Op::RecentTransactions => {
+                let (_, caller) = msg
+                    .fixed_with_leases::<[u8; 0], usize>(1)
+                    .ok_or(ResponseCode::BadArg)?;
+
+                let buf = caller.borrow(0);
+                let info = buf.info().ok_or(ResponseCode::BadArg)?;
+
+                if !info.attributes.contains(LeaseAttributes::WRITE) {
+                    return Err(ResponseCode::BadArg);
+                }
+
+                let size = core::mem::size_of::<TransactionRecord>();
+                let mut n = 0;
+
+                for record in diagnostics.recent().take(info.len / size) {
+                    buf.write_at(n * size, *record)
+                        .ok_or(ResponseCode::BadArg)?;
+                    n += 1;
+                }
+
+                caller.reply(n);
+                Ok(())
+            }
         });
     }
 }
@@ -39,6 +39,7 @@ pub mod ltc4306;
 pub mod max7358;
 pub mod oximux16;
 pub mod pca9545;
+pub mod pca9546;
 pub mod pca9548;
 
 use ringbuf::*;
@@ -64,6 +65,34 @@ pub struct I2cController<'a> {
     pub peripheral: sys_api::Peripheral,
     pub notification: u32,
     pub registers: &'a RegisterBlock,
+
+    /// SCL low (i.e., clock stretching) timeout in milliseconds, if other
+    /// than the default of 25 ms
+    pub timeout_ms: Option<u8>,
+}
+
+///
+/// Where a transaction falls in a chain of transactions that are separated
+/// by repeated starts rather than STOPs; see
+/// [`I2cController::write_read_link`].
+///
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Link {
+    /// A transaction that stands alone
+    Only,
+    First,
+    Middle,
+    Last,
+}
+
+impl Link {
+    fn first(&self) -> bool {
+        matches!(self, Link::Only | Link::First)
+    }
+
+    fn last(&self) -> bool {
+        matches!(self, Link::Only | Link::Last)
+    }
 }
 
 pub struct I2cTargetControl {
@@ -105,6 +134,21 @@ pub trait I2cMuxDriver {
         controller: &I2cController<'_>,
         segment: Option<drv_i2c_api::Segment>,
     ) -> Result<(), drv_i2c_api::ResponseCode>;
+
+    /// Having had a device on the specified (enabled) segment fail to
+    /// respond, check that the mux itself is still present and still has
+    /// the segment enabled, returning `MuxDisconnected` or
+    /// `SegmentDisconnected` if not.  On success, returns a mask of the
+    /// segments that have an interrupt pending (bit 0 denoting `S1`), or 0
+    /// if the mux doesn't support interrupts.
+    fn check_segment(
+        &self,
+        _mux: &I2cMux<'_>,
+        _controller: &I2cController<'_>,
+        _segment: drv_i2c_api::Segment,
+    ) -> Result<u8, drv_i2c_api::ResponseCode> {
+        Ok(0)
+    }
 }
 
 pub struct I2cMux<'a> {
@@ -169,10 +213,24 @@ enum Trace {
     BusySleep,
     Stop,
     RepeatedStart(#[count(children)] bool),
+    PecMismatch(u8, Option<u8>),
+    Reload(Register, u32),
+    WfiTimeout,
 }
 
 counted_ringbuf!(Trace, 48, Trace::None);
 
+///
+/// The controller can be told to expect at most 255 bytes at a time; a longer
+/// transfer must be performed in chunks, with NBYTES reloaded for each.  This
+/// returns the NBYTES value for the chunk that starts at `pos` in a transfer
+/// of `len` bytes, along with whether more chunks will follow it.
+///
+fn chunk(pos: usize, len: usize) -> (u8, bool) {
+    let left = len - pos;
+    (left.min(255) as u8, left > 255)
+}
+
 impl I2cMux<'_> {
     /// A convenience routine to translate an error induced by in-band
     /// management into one that can be returned to a caller
@@ -191,6 +249,52 @@ impl I2cMux<'_> {
         }
     }
 
+    /// Reads the control register of a mux that (like the PCA954x family)
+    /// has only the one register, and hence no register address.  If the
+    /// mux doesn't acknowledge, it has been disconnected.
+    fn read_control(
+        &self,
+        controller: &I2cController<'_>,
+    ) -> Result<u8, drv_i2c_api::ResponseCode> {
+        use drv_i2c_api::ResponseCode;
+
+        let mut rval = 0u8;
+
+        match controller.write_read(
+            self.address,
+            0,
+            |_| Some(0),
+            ReadLength::Fixed(1),
+            |_, byte| {
+                rval = byte;
+                Some(())
+            },
+        ) {
+            Err(ResponseCode::NoDevice) => Err(ResponseCode::MuxDisconnected),
+            Err(code) => Err(self.error_code(code)),
+            Ok(()) => Ok(rval),
+        }
+    }
+
+    /// Reads the control register (as [`read_control`]) of a mux that
+    /// enables segment `Sn` with bit `n - 1`, and checks that `segment` is
+    /// still enabled.  If it isn't, the mux has been reset (or has otherwise
+    /// lost its state) out from under us, and `SegmentDisconnected` is
+    /// returned; otherwise, the register is returned.
+    fn check_control(
+        &self,
+        controller: &I2cController<'_>,
+        segment: drv_i2c_api::Segment,
+    ) -> Result<u8, drv_i2c_api::ResponseCode> {
+        let reg = self.read_control(controller)?;
+
+        if reg & (1 << (segment as u8 - 1)) == 0 {
+            Err(drv_i2c_api::ResponseCode::SegmentDisconnected)
+        } else {
+            Ok(reg)
+        }
+    }
+
     fn configure(
         &self,
         sys: &sys_api::Sys,
@@ -335,16 +439,35 @@ impl I2cController<'_> {
             // (196). Note that these numbers make assumptions about the
             // system's clocking and clock tree configuration; TODO.
             //
+            // A controller with slow, clock-stretching targets may be
+            // configured with a longer timeout, which we scale from the
+            // same clocks -- up to the 12 bits of TIMEOUTA (about 83 ms on
+            // h743).
+            //
             if #[cfg(any(feature = "h743", feature = "h753"))] {
+                let timeouta = match self.timeout_ms {
+                    None => 1220,
+                    Some(ms) => {
+                        (u32::from(ms) * 100_000 / 2048).min(4095) as u16
+                    }
+                };
+
                 i2c.timeoutr.write(|w| { w
                     .timouten().set_bit()           // Enable SCL timeout
-                    .timeouta().bits(1220)          // Timeout value
+                    .timeouta().bits(timeouta)      // Timeout value
                     .tidle().clear_bit()            // Want SCL, not IDLE
                 });
             } else if #[cfg(any(feature = "g030", feature = "g031"))] {
+                let timeouta = match self.timeout_ms {
+                    None => 196,
+                    Some(ms) => {
+                        (u32::from(ms) * 16_000 / 2048).min(4095) as u16
+                    }
+                };
+
                 i2c.timeoutr.write(|w| { w
                     .timouten().set_bit()           // Enable SCL timeout
-                    .timeouta().bits(196)           // Timeout value
+                    .timeouta().bits(timeouta)      // Timeout value
                     .tidle().clear_bit()            // Want SCL, not IDLE
                 });
             } else {
@@ -378,6 +501,20 @@ impl I2cController<'_> {
         i2c.cr1.modify(|_, w| w.pe().set_bit());
     }
 
+    /// Reinitialize the controller from scratch, as at boot.  Unlike
+    /// [`reset`], this also reloads the timing and timeout configuration.
+    pub fn reinitialize(&self) {
+        let i2c = self.registers;
+
+        // Disable PE (which aborts anything in flight)...
+        i2c.cr1.write(|w| w.pe().clear_bit());
+
+        // ...and the timeout, as TIMEOUTA can only be written without it.
+        i2c.timeoutr.reset();
+
+        self.configure();
+    }
+
     /// Reset the controller, as per the datasheet: clear PE, wait for it
     /// to become 0, and set it.
     pub fn reset(&self) {
@@ -412,9 +549,15 @@ impl I2cController<'_> {
     fn check_errors(&self, isr: &Isr) -> Result<(), drv_i2c_api::ResponseCode> {
         let i2c = self.registers;
 
+        //
+        // On losing arbitration, the controller drops back to being a target
+        // and releases the bus on its own:  this isn't a condition that
+        // requires a reset, but rather one that requires trying again once
+        // the winning initiator is done.
+        //
         if isr.arlo().is_lost() {
             i2c.icr.write(|w| w.arlocf().set_bit());
-            return Err(drv_i2c_api::ResponseCode::BusReset);
+            return Err(drv_i2c_api::ResponseCode::ArbitrationLost);
         }
 
         if isr.berr().is_error() {
@@ -434,10 +577,59 @@ impl I2cController<'_> {
     /// A common routine to wait for any of our interrupt-related notification
     /// bits. Note that you'll still want to check the actual interrupt status
     /// bits to distinguish a real interrupt from a stale or mischieviously
-    /// posted notification bit.
+    /// posted notification bit.  If `deadline` (as armed by
+    /// [`with_deadline`]) has passed, this returns `ControllerBusy`.
     ///
-    fn wfi(&self) {
+    fn wfi(&self, deadline: u64) -> Result<(), drv_i2c_api::ResponseCode> {
         sys_recv_notification(self.notification);
+
+        if sys_get_timer().now >= deadline {
+            ringbuf_entry!(Trace::WfiTimeout);
+            return Err(drv_i2c_api::ResponseCode::ControllerBusy);
+        }
+
+        Ok(())
+    }
+
+    ///
+    /// So that a lost interrupt or a wedged controller can't hang the
+    /// server (and with it, every one of its clients), we don't wait for our
+    /// interrupts indefinitely:  this runs `f` with a deadline (to be passed
+    /// to [`wfi`]) for a transaction that moves `nbytes` bytes, counting
+    /// each address phase as a byte.  If no interrupt has arrived when the
+    /// deadline passes, `wfi` returns `ControllerBusy`, which will induce
+    /// the caller to reset the controller.
+    ///
+    fn with_deadline<T>(
+        &self,
+        nbytes: usize,
+        f: impl FnOnce(u64) -> Result<T, drv_i2c_api::ResponseCode>,
+    ) -> Result<T, drv_i2c_api::ResponseCode> {
+        //
+        // A target that stretches the clock is for the controller to
+        // detect, not us -- but the controller's SCL low timeout bounds
+        // only a single stretch, not the transaction.  Targets stretch the
+        // clock (at most) once per byte, so we allow each byte our SCL low
+        // timeout plus a millisecond (about ten times what a byte takes at
+        // 100 kHz), and then add a margin for the scheduling of the server
+        // itself:  a deadline that passes is a controller that has wedged,
+        // not a target that is legally taking its time.
+        //
+        const MARGIN_MS: u64 = 500;
+
+        let per_byte = u64::from(self.timeout_ms.unwrap_or(25)) + 1;
+        let deadline =
+            sys_get_timer().now + MARGIN_MS + per_byte * nbytes as u64;
+
+        //
+        // We have the timer post our own notification bit, which the
+        // callers of wfi already treat as potentially spurious.
+        //
+        sys_set_timer(Some(deadline), self.notification);
+        let result = f(deadline);
+        sys_set_timer(None, self.notification);
+
+        result
     }
 
     fn wait_until_notbusy(&self) -> Result<(), drv_i2c_api::ResponseCode> {
@@ -502,36 +694,184 @@ impl I2cController<'_> {
 
     /// Perform a write to and then a read from the specified device.  Either
     /// the write length or the read length can be zero, but one of these must
-    /// be non-zero.  Additionally, both lengths must be less than 256 bytes:
-    /// the device can support longer buffers, and the implementation could
-    /// be extended in the future to allow them.
+    /// be non-zero.  Transfers of more than 255 bytes (the most that the
+    /// controller can be told to expect at once) are performed in chunks.
     pub fn write_read(
+        &self,
+        addr: u8,
+        wlen: usize,
+        getbyte: impl Fn(usize) -> Option<u8>,
+        rlen: ReadLength,
+        putbyte: impl FnMut(usize, u8) -> Option<()>,
+    ) -> Result<(), drv_i2c_api::ResponseCode> {
+        self.transact(addr, wlen, getbyte, rlen, putbyte, false, Link::Only)
+    }
+
+    /// Like [`write_read`], but with SMBus Packet Error Checking:  if there
+    /// is no read, a PEC is sent after the written bytes; if there is a read,
+    /// the PEC that follows the read bytes is received and checked, with a
+    /// mismatch resulting in [`ResponseCode::BadPec`].
+    ///
+    /// [`ResponseCode::BadPec`]: drv_i2c_api::ResponseCode::BadPec
+    pub fn write_read_pec(
+        &self,
+        addr: u8,
+        wlen: usize,
+        getbyte: impl Fn(usize) -> Option<u8>,
+        rlen: ReadLength,
+        putbyte: impl FnMut(usize, u8) -> Option<()>,
+    ) -> Result<(), drv_i2c_api::ResponseCode> {
+        self.transact(addr, wlen, getbyte, rlen, putbyte, true, Link::Only)
+    }
+
+    /// Like [`write_read`], but as one link in a chain of transactions to
+    /// the same device that are separated by repeated starts rather than
+    /// STOPs (as some devices require of consecutive block reads).  Only
+    /// the first link waits for the bus to be idle, and only the last link
+    /// sends a STOP.  If a link fails, the chain must not be continued -- but
+    /// not every failure ends the transaction:  a link that fails after
+    /// completing its transfer (with an overrun or PEC mismatch) sends a
+    /// STOP, and the controller sends one itself on a NACK, but any other
+    /// failure may leave the bus held until the controller is reset.
+    pub fn write_read_link(
+        &self,
+        addr: u8,
+        wlen: usize,
+        getbyte: impl Fn(usize) -> Option<u8>,
+        rlen: ReadLength,
+        putbyte: impl FnMut(usize, u8) -> Option<()>,
+        link: Link,
+    ) -> Result<(), drv_i2c_api::ResponseCode> {
+        self.transact(addr, wlen, getbyte, rlen, putbyte, false, link)
+    }
+
+    ///
+    /// Waits for the controller to complete a chunk of a transfer of more
+    /// than 255 bytes, and then sets it up for the next chunk (which starts
+    /// at `pos` in a transfer of `len` bytes), returning the end of the new
+    /// chunk.
+    ///
+    fn reload(
+        &self,
+        pos: usize,
+        len: usize,
+        deadline: u64,
+    ) -> Result<usize, drv_i2c_api::ResponseCode> {
+        let i2c = self.registers;
+
+        loop {
+            let isr = i2c.isr.read();
+            ringbuf_entry!(Trace::Reload(Register::ISR, isr.bits()));
+
+            self.check_errors(&isr)?;
+
+            //
+            // The device acknowledged its address before the first chunk,
+            // so a NACK here is of data -- just as if it were at the end of
+            // the write.
+            //
+            if isr.nackf().is_nack() {
+                i2c.icr.write(|w| w.nackcf().set_bit());
+                // Setting ISR.TXE to 1 flushes anything pending there.
+                i2c.isr.write(|w| w.txe().set_bit());
+                return Err(drv_i2c_api::ResponseCode::NoRegister);
+            }
+
+            if isr.tcr().bit_is_set() {
+                break;
+            }
+
+            self.wfi(deadline)?;
+            sys_irq_control(self.notification, true);
+        }
+
+        // Writing a non-zero NBYTES clears TCR and resumes the transfer.
+        let (nbytes, reload) = chunk(pos, len);
+
+        #[rustfmt::skip]
+        i2c.cr2.modify(|_, w| { w
+            .nbytes().bits(nbytes)
+            .reload().bit(reload)
+        });
+
+        Ok(pos + usize::from(nbytes))
+    }
+
+    fn transact(
+        &self,
+        addr: u8,
+        wlen: usize,
+        getbyte: impl Fn(usize) -> Option<u8>,
+        rlen: ReadLength,
+        putbyte: impl FnMut(usize, u8) -> Option<()>,
+        pec: bool,
+        link: Link,
+    ) -> Result<(), drv_i2c_api::ResponseCode> {
+        //
+        // A block read can be as long as 255 bytes, plus its count and PEC;
+        // the write and the read each have an address phase.
+        //
+        let rbytes = match rlen {
+            ReadLength::Fixed(0) => 0,
+            ReadLength::Fixed(rlen) => 1 + rlen,
+            ReadLength::Variable => 1 + 1 + 255,
+        };
+
+        let nbytes = usize::from(wlen > 0) + wlen + rbytes + usize::from(pec);
+
+        //
+        // If we are continuing a chain, the bus is busy because we left it
+        // that way:  our START will be a repeated start.  (We wait for the
+        // bus before arming our deadline, as waiting may sleep -- which
+        // would take our timer.)
+        //
+        if link.first() {
+            self.wait_until_notbusy()?;
+        }
+
+        self.with_deadline(nbytes, |deadline| {
+            self.transact_until(
+                addr, wlen, getbyte, rlen, putbyte, pec, link, deadline,
+            )
+        })
+    }
+
+    fn transact_until(
         &self,
         addr: u8,
         wlen: usize,
         getbyte: impl Fn(usize) -> Option<u8>,
         mut rlen: ReadLength,
         mut putbyte: impl FnMut(usize, u8) -> Option<()>,
+        pec: bool,
+        link: Link,
+        deadline: u64,
     ) -> Result<(), drv_i2c_api::ResponseCode> {
+        //
+        // If we are checking PECs, the PEC byte goes on the end of the
+        // transaction:  after the write if there is no read, and after the
+        // read otherwise.
+        //
+        let wpec = usize::from(pec && rlen == ReadLength::Fixed(0));
+        let rpec = usize::from(pec && rlen != ReadLength::Fixed(0));
+
         // Assert our preconditions as described above
         assert!(wlen > 0 || rlen != ReadLength::Fixed(0));
-        assert!(wlen <= 255);
-
-        if let ReadLength::Fixed(rlen) = rlen {
-            assert!(rlen <= 255);
-        }
 
         let i2c = self.registers;
         let notification = self.notification;
-
-        self.wait_until_notbusy()?;
+        let mut crc = drv_i2c_api::smbus::Pec::new();
 
         if wlen > 0 {
+            crc.write_address(addr);
+
+            let (nbytes, reload) = chunk(0, wlen + wpec);
+
             #[rustfmt::skip]
             i2c.cr2.modify(|_, w| { w
-                .nbytes().bits(wlen as u8)
+                .nbytes().bits(nbytes)
                 .autoend().clear_bit()
-                .reload().clear_bit()
+                .reload().bit(reload)
                 .add10().clear_bit()
                 .sadd().bits((addr << 1).into())
                 .rd_wrn().clear_bit()
@@ -539,8 +879,13 @@ impl I2cController<'_> {
             });
 
             let mut pos = 0;
+            let mut end = usize::from(nbytes);
+
+            while pos < wlen + wpec {
+                if pos == end {
+                    end = self.reload(pos, wlen + wpec, deadline)?;
+                }
 
-            while pos < wlen {
                 loop {
                     let isr = i2c.isr.read();
                     ringbuf_entry!(Trace::Write(Register::ISR, isr.bits()));
@@ -551,20 +896,36 @@ impl I2cController<'_> {
                         i2c.icr.write(|w| w.nackcf().set_bit());
                         // Setting ISR.TXE to 1 flushes anything pending there.
                         i2c.isr.write(|w| w.txe().set_bit());
-                        return Err(drv_i2c_api::ResponseCode::NoDevice);
+
+                        //
+                        // Only a NACK before our first byte is of the
+                        // address; a NACK after it is of data, which must
+                        // not look like an absent device (lest the write be
+                        // retried in its entirety).
+                        //
+                        return Err(if pos == 0 {
+                            drv_i2c_api::ResponseCode::NoDevice
+                        } else {
+                            drv_i2c_api::ResponseCode::NoRegister
+                        });
                     }
 
                     if isr.txis().is_empty() {
                         break;
                     }
 
-                    self.wfi();
+                    self.wfi(deadline)?;
                     sys_irq_control(notification, true);
                 }
 
-                // Get a single byte.
-                let byte =
-                    getbyte(pos).ok_or(drv_i2c_api::ResponseCode::BadArg)?;
+                // Get a single byte -- or our PEC if we're past the end.
+                let byte = if pos < wlen {
+                    getbyte(pos).ok_or(drv_i2c_api::ResponseCode::BadArg)?
+                } else {
+                    crc.value()
+                };
+
+                crc.update(byte);
 
                 // And send it!
                 i2c.txdr.write(|w| w.txdata().bits(byte));
@@ -590,12 +951,13 @@ impl I2cController<'_> {
                     break;
                 }
 
-                self.wfi();
+                self.wfi(deadline)?;
                 sys_irq_control(notification, true);
             }
         }
 
         let mut overrun = false;
+        let mut badpec = false;
 
         if rlen != ReadLength::Fixed(0) {
             //
@@ -604,12 +966,19 @@ impl I2cController<'_> {
             // permit a STOP between a register address write and a subsequent
             // read).
             //
+            crc.read_address(addr);
+
+            let mut end = 1;
+
             if let ReadLength::Fixed(rlen) = rlen {
+                let (nbytes, reload) = chunk(0, rlen + rpec);
+                end = usize::from(nbytes);
+
                 #[rustfmt::skip]
                 i2c.cr2.modify(|_, w| { w
-                    .nbytes().bits(rlen as u8)
+                    .nbytes().bits(nbytes)
                     .autoend().clear_bit()
-                    .reload().clear_bit()
+                    .reload().bit(reload)
                     .add10().clear_bit()
                     .sadd().bits((addr << 1).into())
                     .rd_wrn().set_bit()
@@ -629,16 +998,21 @@ impl I2cController<'_> {
             }
 
             let mut pos = 0;
+            let mut received = None;
 
             loop {
                 if let ReadLength::Fixed(rlen) = rlen {
-                    if pos >= rlen {
+                    if pos >= rlen + rpec {
                         break;
                     }
+
+                    if pos == end {
+                        end = self.reload(pos, rlen + rpec, deadline)?;
+                    }
                 }
 
                 loop {
-                    self.wfi();
+                    self.wfi(deadline)?;
                     sys_irq_control(notification, true);
 
                     let isr = i2c.isr.read();
@@ -663,16 +1037,38 @@ impl I2cController<'_> {
                 let byte: u8 = i2c.rxdr.read().rxdata().bits();
 
                 if rlen == ReadLength::Variable {
+                    crc.update(byte);
+
+                    //
+                    // If we're checking PECs, we need to read one more byte
+                    // than the device claims to have -- which, for a device
+                    // that claims 255 bytes, will take a reload.
+                    //
+                    let len = usize::from(byte);
+                    let (nbytes, reload) = chunk(0, len + rpec);
+
                     #[rustfmt::skip]
                     i2c.cr2.modify(|_, w| { w
-                        .nbytes().bits(byte)
-                        .reload().clear_bit()
+                        .nbytes().bits(nbytes)
+                        .reload().bit(reload)
                     });
 
-                    rlen = ReadLength::Fixed(byte.into());
+                    rlen = ReadLength::Fixed(len);
+                    end = usize::from(nbytes);
                     continue;
                 }
 
+                if let ReadLength::Fixed(rlen) = rlen {
+                    if pos == rlen {
+                        // This is the PEC -- which is not part of the payload
+                        received = Some(byte);
+                        pos += 1;
+                        continue;
+                    }
+                }
+
+                crc.update(byte);
+
                 if !overrun && putbyte(pos, byte).is_none() {
                     //
                     // If we're unable to accept what we just read, we need to
@@ -696,19 +1092,29 @@ impl I2cController<'_> {
 
                 self.check_errors(&isr)?;
 
-                self.wfi();
+                self.wfi(deadline)?;
                 sys_irq_control(notification, true);
             }
+
+            if rpec != 0 && received != Some(crc.value()) {
+                ringbuf_entry!(Trace::PecMismatch(crc.value(), received));
+                badpec = true;
+            }
         }
 
         //
         // Whether we did a write alone, a read alone, or a write followed
-        // by a read, we're done now -- manually send a STOP.
+        // by a read, we're done now -- manually send a STOP (unless the
+        // next link in our chain is to follow with a repeated start).
         //
-        i2c.cr2.modify(|_, w| w.stop().set_bit());
+        if link.last() || overrun || badpec {
+            i2c.cr2.modify(|_, w| w.stop().set_bit());
+        }
 
         if overrun {
             Err(drv_i2c_api::ResponseCode::TooMuchData)
+        } else if badpec {
+            Err(drv_i2c_api::ResponseCode::BadPec)
         } else {
             Ok(())
         }
@@ -731,12 +1137,23 @@ impl I2cController<'_> {
         &self,
         addr: u8,
         ops: &[I2cKonamiCode],
+    ) -> Result<(), drv_i2c_api::ResponseCode> {
+        self.wait_until_notbusy()?;
+
+        self.with_deadline(ops.len(), |deadline| {
+            self.send_konami_code_until(addr, ops, deadline)
+        })
+    }
+
+    fn send_konami_code_until(
+        &self,
+        addr: u8,
+        ops: &[I2cKonamiCode],
+        deadline: u64,
     ) -> Result<(), drv_i2c_api::ResponseCode> {
         let i2c = self.registers;
         let notification = self.notification;
 
-        self.wait_until_notbusy()?;
-
         for op in ops {
             let opval = match *op {
                 I2cKonamiCode::Write => false,
@@ -777,7 +1194,7 @@ impl I2cController<'_> {
                     break;
                 }
 
-                self.wfi();
+                self.wfi(deadline)?;
                 sys_irq_control(notification, true);
             }
         }
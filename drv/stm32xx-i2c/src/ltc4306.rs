@@ -108,6 +108,21 @@ fn read_reg_u8(
     }
 }
 
+fn check_status(
+    mux: &I2cMux<'_>,
+    controller: &I2cController<'_>,
+) -> Result<Register0, ResponseCode> {
+    let reg0 = Register0(read_reg_u8(mux, controller, 0)?);
+
+    if !reg0.not_failed() {
+        Err(ResponseCode::SegmentDisconnected)
+    } else if !reg0.connected() {
+        Err(ResponseCode::MuxDisconnected)
+    } else {
+        Ok(reg0)
+    }
+}
+
 fn write_reg_u8(
     mux: &I2cMux<'_>,
     controller: &I2cController<'_>,
@@ -165,15 +180,54 @@ impl I2cMuxDriver for Ltc4306 {
         }
 
         write_reg_u8(mux, controller, 3, reg3.0)?;
-        let reg0 = Register0(read_reg_u8(mux, controller, 0)?);
-
-        if !reg0.not_failed() {
-            Err(ResponseCode::SegmentDisconnected)
-        } else if !reg0.connected() {
-            Err(ResponseCode::MuxDisconnected)
-        } else {
-            Ok(())
+        check_status(mux, controller).map(|_| ())
+    }
+
+    fn check_segment(
+        &self,
+        mux: &I2cMux<'_>,
+        controller: &I2cController<'_>,
+        segment: Segment,
+    ) -> Result<u8, ResponseCode> {
+        let disconnected = |code: ResponseCode| match code {
+            ResponseCode::MuxMissing => ResponseCode::MuxDisconnected,
+            _ => code,
+        };
+
+        //
+        // Check that our segment is still the one that's connected:  if the
+        // mux has been reset (or switched elsewhere), it won't be.
+        //
+        let reg3 =
+            Register3(read_reg_u8(mux, controller, 3).map_err(disconnected)?);
+
+        let connected = match segment {
+            Segment::S1 => reg3.bus1_connected(),
+            Segment::S2 => reg3.bus2_connected(),
+            Segment::S3 => reg3.bus3_connected(),
+            Segment::S4 => reg3.bus4_connected(),
+            _ => return Err(ResponseCode::SegmentNotFound),
+        };
+
+        if !connected {
+            return Err(ResponseCode::SegmentDisconnected);
         }
+
+        let reg0 = check_status(mux, controller).map_err(disconnected)?;
+
+        let alerts = [
+            reg0.not_alert1(),
+            reg0.not_alert2(),
+            reg0.not_alert3(),
+            reg0.not_alert4(),
+        ];
+
+        // The ALERT inputs are active-low.
+        Ok(alerts
+            .iter()
+            .enumerate()
+            .filter(|(_, &not_alert)| !not_alert)
+            .fold(0, |mask, (i, _)| mask | (1 << i)))
     }
 
     fn reset(
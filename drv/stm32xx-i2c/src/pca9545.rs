@@ -73,6 +73,18 @@ impl I2cMuxDriver for Pca9545 {
         }
     }
 
+    fn check_segment(
+        &self,
+        mux: &I2cMux<'_>,
+        controller: &I2cController<'_>,
+        segment: Segment,
+    ) -> Result<u8, ResponseCode> {
+        let reg = mux.check_control(controller, segment)?;
+
+        // The interrupt bits for channels 0-3 are in the upper nibble.
+        Ok(reg >> 4)
+    }
+
     fn reset(
         &self,
         mux: &I2cMux<'_>,
@@ -0,0 +1,52 @@
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Driver for the PCA9546 I2C mux
+//!
+//! This is a PCA9545 without the interrupt inputs, and is driven as one --
+//! save that it has no interrupts to report.
+
+use crate::pca9545::Pca9545;
+use crate::*;
+use drv_i2c_api::{ResponseCode, Segment};
+
+pub struct Pca9546;
+
+impl I2cMuxDriver for Pca9546 {
+    fn configure(
+        &self,
+        mux: &I2cMux<'_>,
+        controller: &I2cController<'_>,
+        gpio: &sys_api::Sys,
+    ) -> Result<(), drv_i2c_api::ResponseCode> {
+        Pca9545.configure(mux, controller, gpio)
+    }
+
+    fn enable_segment(
+        &self,
+        mux: &I2cMux<'_>,
+        controller: &I2cController<'_>,
+        segment: Option<Segment>,
+    ) -> Result<(), ResponseCode> {
+        Pca9545.enable_segment(mux, controller, segment)
+    }
+
+    fn check_segment(
+        &self,
+        mux: &I2cMux<'_>,
+        controller: &I2cController<'_>,
+        segment: Segment,
+    ) -> Result<u8, ResponseCode> {
+        mux.check_control(controller, segment)?;
+        Ok(0)
+    }
+
+    fn reset(
+        &self,
+        mux: &I2cMux<'_>,
+        gpio: &sys_api::Sys,
+    ) -> Result<(), drv_i2c_api::ResponseCode> {
+        Pca9545.reset(mux, gpio)
+    }
+}
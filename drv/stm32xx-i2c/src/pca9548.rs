@@ -89,6 +89,18 @@ impl I2cMuxDriver for Pca9548 {
         }
     }
 
+    fn check_segment(
+        &self,
+        mux: &I2cMux<'_>,
+        controller: &I2cController<'_>,
+        segment: Segment,
+    ) -> Result<u8, ResponseCode> {
+        mux.check_control(controller, segment)?;
+
+        // This part has no interrupt inputs.
+        Ok(0)
+    }
+
     fn reset(
         &self,
         mux: &I2cMux<'_>,
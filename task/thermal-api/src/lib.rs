@@ -170,6 +170,16 @@ impl From<drv_i2c_devices::tmp117::Error> for SensorReadError {
     }
 }
 
+impl From<drv_i2c_devices::emc1413::Error> for SensorReadError {
+    fn from(s: drv_i2c_devices::emc1413::Error) -> Self {
+        use drv_i2c_devices::emc1413::Error::*;
+        match s {
+            BadRegisterRead { code, .. } => Self::I2cError(code),
+            BadRegisterWrite { .. } => panic!(),
+        }
+    }
+}
+
 impl From<drv_i2c_devices::tmp451::Error> for SensorReadError {
     fn from(s: drv_i2c_devices::tmp451::Error) -> Self {
         use drv_i2c_devices::tmp451::Error::*;